@@ -0,0 +1,4 @@
+# check for interactive bash and that we haven't already been sourced.
+if [ -n "${BASH_VERSION-}" -a -n "${PS1-}" -a -z "${BASH_COMPLETION_VERSINFO-}" ]; then
+    . /usr/share/bash-completion/bash_completion
+fi
@@ -0,0 +1,568 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, VecDeque},
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use forensic_rs::{
+    prelude::{ForensicError, ForensicResult},
+    traits::vfs::{VDirEntry, VFileType, VMetadata, VirtualFileSystem},
+};
+
+use crate::shared::{absolute_path, decompress_with_limit, path_components, MAX_SYMLINK_FOLLOWS};
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFDIR: u32 = 0o040000;
+//Info-ZIP extra fields with the unix mtime and the owner
+const ZIP_EXTENDED_TIMESTAMP: u16 = 0x5455;
+const ZIP_UNIX_OWNER: u16 = 0x7875;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum ArchiveEntryKind {
+    #[default]
+    File,
+    Directory,
+    Symlink(PathBuf),
+    //tar hard links point to another member of the archive
+    HardLink(PathBuf),
+}
+
+//what the archive stores about a member, VMetadata has no room for the owner and the permissions
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ArchiveEntry {
+    pub path: PathBuf,
+    pub kind: ArchiveEntryKind,
+    pub size: u64,
+    //permission bits, without the file type
+    pub mode: Option<u32>,
+    pub uid: Option<u64>,
+    pub gid: Option<u64>,
+    pub user_name: Option<String>,
+    pub group_name: Option<String>,
+    pub modified: Option<usize>,
+    pub accessed: Option<usize>,
+}
+
+//members by absolute path, directories missing from the archive are created from the paths of their children
+#[derive(Debug, Default, Clone)]
+struct ArchiveIndex {
+    entries: BTreeMap<PathBuf, ArchiveEntry>,
+    children: BTreeMap<PathBuf, BTreeSet<String>>,
+}
+
+impl ArchiveIndex {
+    fn insert(&mut self, entry: ArchiveEntry) {
+        let mut path = entry.path.clone();
+        while let Some(parent) = path.parent().map(|v| v.to_path_buf()) {
+            let name = match path.file_name() {
+                Some(v) => v.to_string_lossy().to_string(),
+                None => break,
+            };
+            self.children.entry(parent.clone()).or_default().insert(name);
+            if parent != Path::new("/") && !self.entries.contains_key(&parent) {
+                self.entries.insert(
+                    parent.clone(),
+                    ArchiveEntry {
+                        path: parent.clone(),
+                        kind: ArchiveEntryKind::Directory,
+                        ..Default::default()
+                    },
+                );
+            }
+            path = parent;
+        }
+        //an explicit member replaces the directory created for its children
+        self.entries.insert(entry.path.clone(), entry);
+    }
+
+    //path of the member after following every symbolic link, hard links are resolved by the caller
+    fn resolve(&self, path: &Path) -> ForensicResult<PathBuf> {
+        let mut resolved = PathBuf::from("/");
+        let mut remaining: VecDeque<String> = path_components(path).into();
+        let mut follows = 0;
+        while let Some(name) = remaining.pop_front() {
+            if name == ".." {
+                resolved.pop();
+                continue;
+            }
+            let candidate = resolved.join(&name);
+            match self.entries.get(&candidate).map(|v| &v.kind) {
+                Some(ArchiveEntryKind::Symlink(target)) => {
+                    follows += 1;
+                    if follows > MAX_SYMLINK_FOLLOWS {
+                        return Err(ForensicError::Other(format!("Too many levels of symbolic links: {}", path.display())));
+                    }
+                    if target.is_absolute() {
+                        resolved = PathBuf::from("/");
+                    }
+                    for component in path_components(target).into_iter().rev() {
+                        remaining.push_front(component);
+                    }
+                }
+                Some(_) => resolved = candidate,
+                None => return Err(ForensicError::Missing),
+            }
+        }
+        Ok(resolved)
+    }
+
+    //member holding the data of the path, following symbolic and hard links
+    fn data_entry(&self, path: &Path) -> ForensicResult<&ArchiveEntry> {
+        let mut entry = self.entries.get(&self.resolve(path)?).ok_or(ForensicError::Missing)?;
+        for _ in 0..MAX_SYMLINK_FOLLOWS {
+            match &entry.kind {
+                ArchiveEntryKind::HardLink(target) => {
+                    entry = self.entries.get(&self.resolve(target)?).ok_or(ForensicError::Missing)?
+                }
+                _ => return Ok(entry),
+            }
+        }
+        Err(ForensicError::Other(format!("Too many levels of hard links: {}", path.display())))
+    }
+
+    //the parent directories are resolved but not the member itself
+    fn entry(&self, path: &Path) -> Option<&ArchiveEntry> {
+        let path = absolute_path(path);
+        let parent = self.resolve(path.parent()?).ok()?;
+        self.entries.get(&parent.join(path.file_name()?))
+    }
+
+    fn metadata(&self, path: &Path) -> ForensicResult<VMetadata> {
+        let root_directory = ArchiveEntry {
+            kind: ArchiveEntryKind::Directory,
+            ..Default::default()
+        };
+        //dangling symbolic links are reported as links instead of failing, they are evidence too
+        let entry = match self.data_entry(path) {
+            Ok(v) => v,
+            Err(_e) if absolute_path(path) == Path::new("/") => &root_directory,
+            Err(e) => self.entry(path).ok_or(e)?,
+        };
+        Ok(VMetadata {
+            created: None,
+            accessed: entry.accessed,
+            modified: entry.modified,
+            file_type: match entry.kind {
+                ArchiveEntryKind::Directory => VFileType::Directory,
+                ArchiveEntryKind::Symlink(_) => VFileType::Symlink,
+                _ => VFileType::File,
+            },
+            size: entry.size,
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> ForensicResult<Vec<VDirEntry>> {
+        let directory = self.resolve(path)?;
+        let children = match self.children.get(&directory) {
+            Some(v) => v,
+            None if self.entries.get(&directory).map(|v| &v.kind) == Some(&ArchiveEntryKind::Directory) => {
+                return Ok(Vec::new())
+            }
+            None => return Err(ForensicError::Missing),
+        };
+        Ok(children
+            .iter()
+            .map(|name| match self.entries.get(&directory.join(name)).map(|v| &v.kind) {
+                Some(ArchiveEntryKind::Directory) => VDirEntry::Directory(name.clone()),
+                Some(ArchiveEntryKind::Symlink(_)) => VDirEntry::Symlink(name.clone()),
+                _ => VDirEntry::File(name.clone()),
+            })
+            .collect())
+    }
+
+    fn read_link(&self, path: &Path) -> ForensicResult<PathBuf> {
+        match self.entry(path).map(|v| &v.kind) {
+            Some(ArchiveEntryKind::Symlink(target)) => Ok(target.clone()),
+            Some(_) => Err(ForensicError::BadFormat),
+            None => Err(ForensicError::Missing),
+        }
+    }
+}
+
+//default limit of the decompressed tar and of every decompressed zip member, the _with_limit constructors change it
+pub const MAX_ARCHIVE_SIZE: u64 = 1 << 30;
+
+//tar archive, plain or compressed with gzip, xz, bzip2 or zstd, kept decompressed in memory. Compressed tars cannot
+//be seeked so the whole archive is decompressed once and must fit in RAM, larger ones than the limit fail
+pub struct TarFileSystem {
+    index: ArchiveIndex,
+    contents: Vec<u8>,
+    //position and size of the data of every regular file inside the decompressed archive
+    data: HashMap<PathBuf, (usize, usize)>,
+}
+
+impl TarFileSystem {
+    pub fn open<P: AsRef<Path>>(path: P) -> ForensicResult<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(contents: Vec<u8>) -> ForensicResult<Self> {
+        Self::from_bytes_with_limit(contents, MAX_ARCHIVE_SIZE)
+    }
+
+    pub fn from_bytes_with_limit(contents: Vec<u8>, limit: u64) -> ForensicResult<Self> {
+        let contents = decompress_with_limit(contents, limit)?;
+        let mut index = ArchiveIndex::default();
+        let mut data = HashMap::new();
+        {
+            let mut archive = tar::Archive::new(Cursor::new(contents.as_slice()));
+            let entries = archive.entries().map_err(|_| ForensicError::BadFormat)?;
+            for entry in entries {
+                let mut entry = entry.map_err(|_| ForensicError::BadFormat)?;
+                let path = match entry.path() {
+                    Ok(v) => absolute_path(&v),
+                    Err(_e) => continue,
+                };
+                if path == Path::new("/") {
+                    continue;
+                }
+                let link_name = entry.link_name().ok().flatten().map(|v| v.to_path_buf());
+                let kind = match (entry.header().entry_type(), link_name) {
+                    (tar::EntryType::Directory, _) => ArchiveEntryKind::Directory,
+                    (tar::EntryType::Symlink, Some(target)) => ArchiveEntryKind::Symlink(target),
+                    (tar::EntryType::Link, Some(target)) => ArchiveEntryKind::HardLink(absolute_path(&target)),
+                    (tar::EntryType::Regular | tar::EntryType::Continuous | tar::EntryType::GNUSparse, _) => {
+                        ArchiveEntryKind::File
+                    }
+                    //devices, fifos and global headers have no data to read
+                    _ => continue,
+                };
+                let header = entry.header();
+                let mut archive_entry = ArchiveEntry {
+                    path: path.clone(),
+                    kind,
+                    size: entry.size(),
+                    mode: header.mode().ok().map(|v| v & 0o7777),
+                    uid: header.uid().ok(),
+                    gid: header.gid().ok(),
+                    user_name: header.username().ok().flatten().map(|v| v.to_string()),
+                    group_name: header.groupname().ok().flatten().map(|v| v.to_string()),
+                    modified: header.mtime().ok().map(|v| v as usize),
+                    accessed: None,
+                };
+                //pax headers keep times and ids that do not fit in the ustar fields
+                if let Ok(Some(extensions)) = entry.pax_extensions() {
+                    for extension in extensions.flatten() {
+                        let value = extension.value().unwrap_or_default();
+                        let seconds = value.split('.').next().unwrap_or_default().parse::<usize>().ok();
+                        match extension.key().unwrap_or_default() {
+                            "mtime" => archive_entry.modified = seconds.or(archive_entry.modified),
+                            "atime" => archive_entry.accessed = seconds,
+                            "uid" => archive_entry.uid = value.parse().ok().or(archive_entry.uid),
+                            "gid" => archive_entry.gid = value.parse().ok().or(archive_entry.gid),
+                            _ => {}
+                        }
+                    }
+                }
+                if archive_entry.kind == ArchiveEntryKind::File {
+                    data.insert(path, (entry.raw_file_position() as usize, entry.size() as usize));
+                }
+                index.insert(archive_entry);
+            }
+        }
+        Ok(Self { index, contents, data })
+    }
+
+    //owner, permissions and link target of a member, without following symbolic links
+    pub fn entry(&self, path: &Path) -> Option<&ArchiveEntry> {
+        self.index.entry(path)
+    }
+
+    pub fn read_link(&self, path: &Path) -> ForensicResult<PathBuf> {
+        self.index.read_link(path)
+    }
+
+    fn file_data(&self, path: &Path) -> ForensicResult<&[u8]> {
+        let entry = self.index.data_entry(path)?;
+        let (position, size) = match self.data.get(&entry.path) {
+            Some(v) => *v,
+            None => return Err(ForensicError::BadFormat),
+        };
+        self.contents.get(position..position + size).ok_or(ForensicError::NoMoreData)
+    }
+}
+
+impl VirtualFileSystem for TarFileSystem {
+    fn read_to_string(&mut self, path: &Path) -> ForensicResult<String> {
+        String::from_utf8(self.file_data(path)?.to_vec()).map_err(|_| ForensicError::BadFormat)
+    }
+
+    fn read_all(&mut self, path: &Path) -> ForensicResult<Vec<u8>> {
+        Ok(self.file_data(path)?.to_vec())
+    }
+
+    fn read(&mut self, path: &Path, pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+        read_at(self.file_data(path)?, pos, buf)
+    }
+
+    fn metadata(&mut self, path: &Path) -> ForensicResult<VMetadata> {
+        self.index.metadata(path)
+    }
+
+    fn read_dir(&mut self, path: &Path) -> ForensicResult<Vec<VDirEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+//zip archive, members are decompressed when they are read
+pub struct ZipFileSystem {
+    index: ArchiveIndex,
+    archive: zip::ZipArchive<Cursor<Vec<u8>>>,
+    members: HashMap<PathBuf, usize>,
+    //the last member read, read() is called again and again on the same file with growing positions
+    cached_member: Option<(usize, Vec<u8>)>,
+    //members that decompress to more bytes are taken as decompression bombs
+    limit: u64,
+}
+
+impl ZipFileSystem {
+    pub fn open<P: AsRef<Path>>(path: P) -> ForensicResult<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(contents: Vec<u8>) -> ForensicResult<Self> {
+        Self::from_bytes_with_limit(contents, MAX_ARCHIVE_SIZE)
+    }
+
+    pub fn from_bytes_with_limit(contents: Vec<u8>, limit: u64) -> ForensicResult<Self> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(contents)).map_err(|_| ForensicError::BadFormat)?;
+        let mut index = ArchiveIndex::default();
+        let mut members = HashMap::new();
+        for member in 0..archive.len() {
+            let mut file = match archive.by_index(member) {
+                Ok(v) => v,
+                Err(_e) => continue,
+            };
+            let path = absolute_path(Path::new(file.name()));
+            if path == Path::new("/") {
+                continue;
+            }
+            let unix_mode = file.unix_mode();
+            let kind = match unix_mode.map(|v| v & S_IFMT) {
+                //the target of a symbolic link is stored as the contents of the member
+                Some(S_IFLNK) => {
+                    let mut target = String::new();
+                    match file.read_to_string(&mut target) {
+                        Ok(_) => ArchiveEntryKind::Symlink(PathBuf::from(target)),
+                        Err(_e) => continue,
+                    }
+                }
+                Some(S_IFDIR) => ArchiveEntryKind::Directory,
+                _ if file.is_dir() => ArchiveEntryKind::Directory,
+                _ => ArchiveEntryKind::File,
+            };
+            let mut archive_entry = ArchiveEntry {
+                path: path.clone(),
+                kind,
+                size: file.size(),
+                mode: unix_mode.map(|v| v & 0o7777),
+                //DOS times have no time zone, the extended timestamp field is preferred when present
+                modified: file.last_modified().and_then(|v| {
+                    let date = chrono::NaiveDate::from_ymd_opt(v.year() as i32, v.month() as u32, v.day() as u32)?;
+                    let time = date.and_hms_opt(v.hour() as u32, v.minute() as u32, v.second() as u32)?;
+                    usize::try_from(time.and_utc().timestamp()).ok()
+                }),
+                ..Default::default()
+            };
+            parse_zip_extra_fields(file.extra_data().unwrap_or_default(), &mut archive_entry);
+            members.insert(path, member);
+            index.insert(archive_entry);
+        }
+        Ok(Self { index, archive, members, cached_member: None, limit })
+    }
+
+    //owner, permissions and link target of a member, without following symbolic links
+    pub fn entry(&self, path: &Path) -> Option<&ArchiveEntry> {
+        self.index.entry(path)
+    }
+
+    pub fn read_link(&self, path: &Path) -> ForensicResult<PathBuf> {
+        self.index.read_link(path)
+    }
+
+    fn file_data(&mut self, path: &Path) -> ForensicResult<&[u8]> {
+        let entry = self.index.data_entry(path)?;
+        if entry.kind == ArchiveEntryKind::Directory {
+            return Err(ForensicError::BadFormat);
+        }
+        let member = *self.members.get(&entry.path).ok_or(ForensicError::Missing)?;
+        if !matches!(&self.cached_member, Some((cached, _)) if *cached == member) {
+            let file = self.archive.by_index(member).map_err(|_| ForensicError::BadFormat)?;
+            let mut contents = Vec::new();
+            file.take(self.limit + 1).read_to_end(&mut contents)?;
+            if contents.len() as u64 > self.limit {
+                return Err(ForensicError::BadFormat);
+            }
+            self.cached_member = Some((member, contents));
+        }
+        match &self.cached_member {
+            Some((_, contents)) => Ok(contents),
+            None => Err(ForensicError::Missing),
+        }
+    }
+}
+
+impl VirtualFileSystem for ZipFileSystem {
+    fn read_to_string(&mut self, path: &Path) -> ForensicResult<String> {
+        String::from_utf8(self.file_data(path)?.to_vec()).map_err(|_| ForensicError::BadFormat)
+    }
+
+    fn read_all(&mut self, path: &Path) -> ForensicResult<Vec<u8>> {
+        Ok(self.file_data(path)?.to_vec())
+    }
+
+    fn read(&mut self, path: &Path, pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+        read_at(self.file_data(path)?, pos, buf)
+    }
+
+    fn metadata(&mut self, path: &Path) -> ForensicResult<VMetadata> {
+        self.index.metadata(path)
+    }
+
+    fn read_dir(&mut self, path: &Path) -> ForensicResult<Vec<VDirEntry>> {
+        self.index.read_dir(path)
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+//"UT" holds flags and the mtime, "ux" the uid and gid with variable sizes
+fn parse_zip_extra_fields(extra_data: &[u8], archive_entry: &mut ArchiveEntry) {
+    let mut offset = 0;
+    while offset + 4 <= extra_data.len() {
+        let id = u16::from_le_bytes([extra_data[offset], extra_data[offset + 1]]);
+        let size = u16::from_le_bytes([extra_data[offset + 2], extra_data[offset + 3]]) as usize;
+        let field = match extra_data.get(offset + 4..offset + 4 + size) {
+            Some(v) => v,
+            None => return,
+        };
+        match id {
+            ZIP_EXTENDED_TIMESTAMP if field.len() >= 5 && field[0] & 1 == 1 => {
+                let modified = i32::from_le_bytes([field[1], field[2], field[3], field[4]]);
+                archive_entry.modified = usize::try_from(modified).ok();
+            }
+            ZIP_UNIX_OWNER if field.len() >= 2 => {
+                let uid_size = field[1] as usize;
+                let uid = field.get(2..2 + uid_size).map(little_endian_number);
+                let gid_size = field.get(2 + uid_size).copied().unwrap_or_default() as usize;
+                let gid = field.get(3 + uid_size..3 + uid_size + gid_size).map(little_endian_number);
+                archive_entry.uid = uid;
+                archive_entry.gid = gid;
+            }
+            _ => {}
+        }
+        offset += 4 + size;
+    }
+}
+
+fn little_endian_number(bytes: &[u8]) -> u64 {
+    bytes.iter().rev().fold(0, |number, byte| (number << 8) | *byte as u64)
+}
+
+fn read_at(contents: &[u8], pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+    let start = (pos as usize).min(contents.len());
+    let length = buf.len().min(contents.len() - start);
+    buf[..length].copy_from_slice(&contents[start..start + length]);
+    Ok(length)
+}
+
+#[cfg(test)]
+mod archive_tests {
+    use super::*;
+    use crate::prelude::{SystemInfo, UserInfo};
+
+    fn triage_path(file_name: &str) -> PathBuf {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        Path::new(&base_path).join("artifacts/triage").join(file_name)
+    }
+
+    #[test]
+    fn should_read_compressed_tar_triage_packages() {
+        for file_name in ["triage.tar.gz", "triage.tar.xz", "triage.tar.zst"] {
+            let mut vfs = TarFileSystem::open(triage_path(file_name)).expect("Should open tar");
+
+            //the loaders run unchanged on the archive
+            let system_info = SystemInfo::load(&mut vfs).expect("Should load passwd from the archive");
+            let users: Vec<&str> = system_info.users.iter().map(|v| v.name.as_str()).collect();
+            assert_eq!(vec!["root", "forensicrs"], users);
+            let user_info = UserInfo::get_user_info("forensicrs".to_string(), &mut vfs).unwrap();
+            assert_eq!("sudo", user_info.groups[1].name);
+
+            let shadow = vfs.entry(Path::new("/etc/shadow")).unwrap();
+            assert_eq!(Some(0o640), shadow.mode);
+            assert_eq!(Some(42), shadow.gid);
+            assert_eq!(Some("shadow"), shadow.group_name.as_deref());
+            assert_eq!(Some(1700000000), shadow.modified);
+            let history = vfs.metadata(Path::new("/home/forensicrs/.bash_history")).unwrap();
+            assert_eq!(Some(1700000000), history.modified);
+            assert_eq!(Some(1000), vfs.entry(Path::new("/home/forensicrs/.bash_history")).unwrap().uid);
+
+            //absolute, relative and hard links
+            assert_eq!(
+                PathBuf::from("/usr/share/zoneinfo/UTC"),
+                vfs.read_link(Path::new("/etc/localtime")).unwrap()
+            );
+            assert_eq!("TZif2 placeholder\n", vfs.read_to_string(Path::new("/etc/localtime")).unwrap());
+            assert!(vfs.read_to_string(Path::new("/home/forensicrs/.profile_link")).unwrap().starts_with("root:"));
+            assert_eq!(b"\x7fELF python\n".to_vec(), vfs.read_all(Path::new("/usr/bin/python3")).unwrap());
+            assert!(vfs.read_all(Path::new("/tmp/loop")).is_err());
+
+            let mut buf = [0u8; 4];
+            assert_eq!(4, vfs.read(Path::new("/etc/passwd"), 5, &mut buf).unwrap());
+            assert_eq!(b"x:0:", &buf);
+
+            let home: Vec<String> = vfs.read_dir(Path::new("/home/forensicrs")).unwrap().iter().map(|v| v.to_string()).collect();
+            assert_eq!(vec![".bash_history", ".profile_link"], home);
+            assert!(matches!(vfs.read_dir(Path::new("/etc")).unwrap()[1], VDirEntry::Symlink(_)));
+            assert!(vfs.metadata(Path::new("/usr/share")).unwrap().is_dir());
+        }
+    }
+
+    #[test]
+    fn should_read_zip_triage_package() {
+        let mut vfs = ZipFileSystem::open(triage_path("triage.zip")).expect("Should open zip");
+
+        let system_info = SystemInfo::load(&mut vfs).expect("Should load passwd from the archive");
+        assert_eq!(2, system_info.users.len());
+
+        let history = vfs.entry(Path::new("/home/forensicrs/.bash_history")).unwrap();
+        assert_eq!(Some(0o600), history.mode);
+        assert_eq!(Some(1000), history.uid);
+        assert_eq!(Some(1000), history.gid);
+        assert_eq!(Some(1700000000), history.modified);
+
+        assert_eq!(
+            PathBuf::from("/usr/share/zoneinfo/UTC"),
+            vfs.read_link(Path::new("/etc/localtime")).unwrap()
+        );
+        assert_eq!("TZif2 placeholder\n", vfs.read_to_string(Path::new("/etc/localtime")).unwrap());
+        let root: Vec<String> = vfs.read_dir(Path::new("/")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["etc", "home", "usr"], root);
+
+        //reads by parts switching between members
+        let mut buf = [0u8; 5];
+        assert_eq!(5, vfs.read(Path::new("/etc/localtime"), 0, &mut buf).unwrap());
+        assert_eq!(b"TZif2", &buf);
+        assert!(vfs.read_all(Path::new("/etc/passwd")).unwrap().starts_with(b"root:"));
+        assert_eq!(5, vfs.read(Path::new("/etc/localtime"), 6, &mut buf).unwrap());
+        assert_eq!(b"place", &buf);
+    }
+
+    #[test]
+    fn should_reject_archives_over_the_limit() {
+        let tar = std::fs::read(triage_path("triage.tar.gz")).unwrap();
+        assert!(matches!(TarFileSystem::from_bytes_with_limit(tar, 1024), Err(ForensicError::BadFormat)));
+
+        //zip members are checked when they are read
+        let zip = std::fs::read(triage_path("triage.zip")).unwrap();
+        let mut vfs = ZipFileSystem::from_bytes_with_limit(zip, 8).unwrap();
+        assert!(vfs.read_all(Path::new("/etc/passwd")).is_err());
+    }
+}
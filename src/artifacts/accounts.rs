@@ -0,0 +1,303 @@
+pub use crate::prelude::{SystemInfo, UserInfo};
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    collections::HashMap,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//names of service accounts created by the usual distributions and packages
+const SERVICE_ACCOUNT_NAMES: [&str; 56] = [
+    "root", "daemon", "bin", "sys", "sync", "games", "man", "lp", "mail", "news", "uucp", "proxy",
+    "www-data", "backup", "list", "irc", "gnats", "nobody", "systemd-network", "systemd-resolve",
+    "systemd-timesync", "systemd-oom", "systemd-coredump", "systemd-journal", "messagebus",
+    "syslog", "_apt", "tss", "uuidd", "tcpdump", "sshd", "sssd", "avahi", "avahi-autoipd",
+    "usbmux", "dnsmasq", "kernoops", "rtkit", "whoopsie", "colord", "geoclue", "pulse", "saned",
+    "hplip", "gdm", "lightdm", "polkitd", "postfix", "mysql", "postgres", "apache", "nginx",
+    "ftp", "operator", "adm", "halt",
+];
+
+//shells that do not allow an interactive login
+const NON_INTERACTIVE_SHELLS: [&str; 5] = ["nologin", "false", "sync", "shutdown", "halt"];
+//login runs /bin/sh for an empty shell field
+const DEFAULT_SHELL: &str = "/bin/sh";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum AccountFindingKind {
+    #[default]
+    DuplicateRootUid,
+    SystemAccountInteractiveShell,
+    SystemAccountPassword,
+    MissingHome,
+    //guessed from homes shared with an earlier account or named after another account
+    PossiblyForeignHome,
+    MissingShadowEntry,
+    UnlistedShell,
+    ImitatedServiceName,
+}
+
+//every finding points to the line of /etc/passwd or /etc/shadow that caused it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AccountFinding {
+    pub kind: AccountFindingKind,
+    pub username: String,
+    pub description: String,
+    pub path: PathBuf,
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AccountAnalysis {
+    pub findings: Vec<AccountFinding>,
+}
+
+//a line of a colon separated account file
+#[derive(Debug, Default, Clone, PartialEq)]
+struct AccountLine {
+    path: PathBuf,
+    line_number: usize,
+    line: String,
+}
+
+impl AccountAnalysis {
+    pub fn analyze(system_info: &SystemInfo, vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut analysis = Self::default();
+        let passwd_lines = read_account_file(vfs, Path::new("/etc/passwd"));
+        //without shadow or shells file the checks that depend on them are not done
+        let shadow_file = vfs.read_to_string(Path::new("/etc/shadow")).ok();
+        let shadow_lines = read_account_file(vfs, Path::new("/etc/shadow"));
+        let shells: Option<Vec<String>> = vfs.read_to_string(Path::new("/etc/shells")).ok().map(|v| {
+            v.lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .collect()
+        });
+
+        let root_accounts = system_info.users.iter().filter(|v| v.id == 0).count();
+        //a name can be repeated, the nth account with a name comes from the nth valid line with it
+        let mut seen_names: HashMap<&str, usize> = HashMap::new();
+        for user in &system_info.users {
+            let occurrence = seen_names.entry(user.name.as_str()).or_insert(0);
+            *occurrence += 1;
+            let passwd_line = passwd_lines
+                .get(&user.name)
+                .and_then(|v| v.iter().filter(|line| UserInfo::parse_passwd_line(&line.line).is_ok()).nth(*occurrence - 1))
+                .cloned()
+                .unwrap_or_default();
+            let shell = login_shell(&user.shell);
+            let interactive = is_interactive_shell(shell);
+            let system_account = user.id != 0 && (user.id < 1000 || user.id == 65534);
+
+            if user.id == 0 && root_accounts > 1 && user.name != "root" {
+                analysis.add(AccountFindingKind::DuplicateRootUid, user,
+                    "account shares UID 0 with root".to_string(), &passwd_line);
+            }
+            if system_account && interactive {
+                analysis.add(AccountFindingKind::SystemAccountInteractiveShell, user,
+                    format!("system account with interactive shell {}", shell), &passwd_line);
+            }
+            if interactive && vfs.metadata(&user.home).is_err() {
+                analysis.add(AccountFindingKind::MissingHome, user,
+                    format!("home directory {} does not exist", user.home.display()), &passwd_line);
+            }
+            if interactive {
+                if let Some(owner) = likely_home_owner(system_info, user) {
+                    analysis.add(AccountFindingKind::PossiblyForeignHome, user,
+                        format!("home directory {} may belong to {}", user.home.display(), owner), &passwd_line);
+                }
+            }
+            if let Some(shells) = &shells {
+                if interactive && !shells.iter().any(|v| v == shell) {
+                    analysis.add(AccountFindingKind::UnlistedShell, user,
+                        format!("shell {} is not listed in /etc/shells", shell), &passwd_line);
+                }
+            }
+            if let Some(service_name) = imitated_service_name(&user.name) {
+                analysis.add(AccountFindingKind::ImitatedServiceName, user,
+                    format!("account name looks like the service account {}", service_name), &passwd_line);
+            }
+
+            if shadow_file.is_none() {
+                continue;
+            }
+            //login reads the first shadow line of a name, it is the one every duplicate account uses
+            let shadow_line = shadow_lines.get(&user.name).and_then(|v| v.get(*occurrence - 1).or(v.first()));
+            match shadow_line {
+                Some(shadow_line) => {
+                    let password = shadow_line.line.split(':').nth(1).unwrap_or_default();
+                    if system_account && password.is_empty() {
+                        analysis.add(AccountFindingKind::SystemAccountPassword, user,
+                            "system account with an empty password".to_string(), shadow_line);
+                    } else if system_account && !password.starts_with('!') && !password.starts_with('*') {
+                        analysis.add(AccountFindingKind::SystemAccountPassword, user,
+                            "system account with a password set".to_string(), shadow_line);
+                    }
+                }
+                None => analysis.add(AccountFindingKind::MissingShadowEntry, user,
+                    "account has no entry in /etc/shadow".to_string(), &passwd_line),
+            }
+        }
+        Ok(analysis)
+    }
+
+    fn add(&mut self, kind: AccountFindingKind, user: &UserInfo, description: String, line: &AccountLine) {
+        self.findings.push(AccountFinding {
+            kind,
+            username: user.name.clone(),
+            description,
+            path: line.path.clone(),
+            line_number: line.line_number,
+            line: line.line.clone(),
+        });
+    }
+}
+
+//lines of the file for each account name, in file order
+fn read_account_file(vfs: &mut impl VirtualFileSystem, path: &Path) -> HashMap<String, Vec<AccountLine>> {
+    let mut account_lines = HashMap::new();
+    let contents = match vfs.read_to_string(path) {
+        Ok(v) => v,
+        Err(_e) => return account_lines,
+    };
+    for (line_number, line) in contents.lines().enumerate() {
+        let name = line.split(':').next().unwrap_or_default().trim();
+        if name.is_empty() || name.starts_with('#') {
+            continue;
+        }
+        account_lines.entry(name.to_string()).or_insert_with(Vec::new).push(AccountLine {
+            path: path.to_path_buf(),
+            line_number: line_number + 1,
+            line: line.to_string(),
+        });
+    }
+    account_lines
+}
+
+fn login_shell(shell: &str) -> &str {
+    match shell.is_empty() {
+        true => DEFAULT_SHELL,
+        false => shell,
+    }
+}
+
+fn is_interactive_shell(shell: &str) -> bool {
+    let shell_name = login_shell(shell).rsplit('/').next().unwrap_or_default();
+    !shell_name.is_empty() && !NON_INTERACTIVE_SHELLS.contains(&shell_name)
+}
+
+//only a guess from the paths: the home of an earlier interactive account or a home under /home/<other account>
+fn likely_home_owner(system_info: &SystemInfo, user: &UserInfo) -> Option<String> {
+    for other in &system_info.users {
+        if other.name == user.name {
+            break;
+        }
+        if other.home == user.home && is_interactive_shell(&other.shell) {
+            return Some(other.name.clone());
+        }
+    }
+    let home_owner_name = user.home.strip_prefix("/home").ok()?.iter().next()?.to_string_lossy().to_string();
+    if home_owner_name != user.name && system_info.users.iter().any(|v| v.name == home_owner_name) {
+        return Some(home_owner_name);
+    }
+    None
+}
+
+//names at one edit (two for long names) of a service account, or equal once digits are read as letters
+fn imitated_service_name(name: &str) -> Option<&'static str> {
+    if name.len() < 4 || SERVICE_ACCOUNT_NAMES.contains(&name) {
+        return None;
+    }
+    let normalized: String = name
+        .chars()
+        .map(|v| match v {
+            '0' => 'o',
+            '1' => 'l',
+            '3' => 'e',
+            '5' => 's',
+            v => v.to_ascii_lowercase(),
+        })
+        .collect();
+    let max_distance = if name.len() >= 8 { 2 } else { 1 };
+    SERVICE_ACCOUNT_NAMES
+        .iter()
+        .filter(|service_name| service_name.len() >= 4)
+        .find(|service_name| {
+            **service_name == normalized || edit_distance(service_name, &normalized) <= max_distance
+        })
+        .copied()
+}
+
+fn edit_distance(first: &str, second: &str) -> usize {
+    let second: Vec<char> = second.chars().collect();
+    let mut previous: Vec<usize> = (0..=second.len()).collect();
+    for (i, first_char) in first.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, second_char) in second.iter().enumerate() {
+            let substitution = previous[j] + usize::from(first_char != *second_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[second.len()]
+}
+
+#[cfg(test)]
+mod accounts_tests {
+    use super::*;
+
+    #[test]
+    fn should_detect_account_anomalies() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let system_info = SystemInfo::load(&mut vfs).expect("Should load users");
+        let analysis = AccountAnalysis::analyze(&system_info, &mut vfs).expect("Should analyze accounts");
+
+        let finding = |kind: AccountFindingKind, username: &str| {
+            analysis
+                .findings
+                .iter()
+                .find(|v| v.kind == kind && v.username == username)
+                .cloned()
+        };
+
+        let duplicate_root = finding(AccountFindingKind::DuplicateRootUid, "toor").unwrap();
+        assert_eq!(PathBuf::from("/etc/passwd"), duplicate_root.path);
+        assert_eq!(52, duplicate_root.line_number);
+        assert_eq!("toor:x:0:0:root:/root:/bin/bash", duplicate_root.line);
+        assert!(finding(AccountFindingKind::DuplicateRootUid, "root").is_none());
+        //a second games account gets its own line, not the one of the first games
+        let duplicate_name = finding(AccountFindingKind::DuplicateRootUid, "games").unwrap();
+        assert_eq!(59, duplicate_name.line_number);
+        assert_eq!("games:x:0:0:games:/usr/games:/bin/bash", duplicate_name.line);
+        assert!(finding(AccountFindingKind::PossiblyForeignHome, "toor").is_some());
+
+        let password = finding(AccountFindingKind::SystemAccountPassword, "games").unwrap();
+        assert_eq!(PathBuf::from("/etc/shadow"), password.path);
+        assert_eq!(6, password.line_number);
+
+        assert!(finding(AccountFindingKind::MissingShadowEntry, "mosquitto").is_some());
+        assert!(finding(AccountFindingKind::SystemAccountInteractiveShell, "systemd-netwrk").is_some());
+        assert!(finding(AccountFindingKind::UnlistedShell, "systemd-netwrk").is_some());
+        assert!(finding(AccountFindingKind::PossiblyForeignHome, "systemd-netwrk").is_some());
+        //an empty shell field is /bin/sh
+        let empty_shell = finding(AccountFindingKind::SystemAccountInteractiveShell, "svc-sync").unwrap();
+        assert!(empty_shell.description.ends_with("/bin/sh"));
+        assert!(finding(AccountFindingKind::UnlistedShell, "svc-sync").is_none());
+        let imitation = finding(AccountFindingKind::ImitatedServiceName, "systemd-netwrk").unwrap();
+        assert!(imitation.description.ends_with("systemd-network"));
+
+        assert!(analysis.findings.iter().all(|v| v.username != "forensicrs"));
+        assert!(analysis
+            .findings
+            .iter()
+            .all(|v| v.kind != AccountFindingKind::ImitatedServiceName || v.username == "systemd-netwrk"));
+    }
+}
@@ -30,22 +30,77 @@ impl AuthorizedKey {
     ) -> ForensicResult<Vec<Self>> {
         let authorized_keys = vfs.read_to_string(
             user_home_path.join(".ssh/authorized_keys").as_path())?;
-    
+
+        Ok(Self::parse_authorized_keys(&authorized_keys))
+    }
+
+    //reads the files of the AuthorizedKeysFile directive in effect for the user, missing files are skipped
+    pub fn get_authorized_keys_for_user(
+        vfs: &mut impl VirtualFileSystem,
+        user_info: &UserInfo,
+        authorized_keys_files: &[String],
+    ) -> ForensicResult<Vec<Self>> {
+        let mut system_authorized_keys: Vec<Self> = Vec::new();
+
+        for authorized_keys_file in authorized_keys_files {
+            let path = Self::expand_authorized_keys_path(authorized_keys_file, user_info);
+            let authorized_keys = match vfs.read_to_string(path.as_path()) {
+                Ok(v) => v,
+                Err(_e) => continue,
+            };
+            system_authorized_keys.extend(Self::parse_authorized_keys(&authorized_keys));
+        }
+
+        Ok(system_authorized_keys)
+    }
+
+    //expands the %% %h %u %U tokens of sshd, relative paths start at the user home
+    pub fn expand_authorized_keys_path(authorized_keys_file: &str, user_info: &UserInfo) -> PathBuf {
+        let mut expanded = String::new();
+        let mut chars = authorized_keys_file.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                expanded.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('h') => expanded.push_str(&user_info.home.to_string_lossy()),
+                Some('u') => expanded.push_str(&user_info.name),
+                Some('U') => expanded.push_str(&user_info.id.to_string()),
+                Some(other) => expanded.push(other),
+                None => {}
+            }
+        }
+        let path = PathBuf::from(expanded);
+        if path.is_absolute() {
+            path
+        } else {
+            user_info.home.join(path)
+        }
+    }
+
+    fn parse_authorized_keys(authorized_keys: &str) -> Vec<Self> {
         let reader_groups = std::io::BufReader::new(authorized_keys.as_bytes());
         let mut system_authorized_keys: Vec<Self> = Vec::new();
-    
-        for authorized_key in reader_groups.lines() {
-            let authorized_key = authorized_key?;
-            let captures = AUTHORIZED_KEYS_COMPONENTS.captures(&authorized_key).unwrap();
+
+        for authorized_key in reader_groups.lines().map_while(Result::ok) {
+            let authorized_key = authorized_key.trim();
+            if authorized_key.is_empty() || authorized_key.starts_with('#') {
+                continue;
+            }
+            let captures = match AUTHORIZED_KEYS_COMPONENTS.captures(authorized_key) {
+                Some(v) => v,
+                None => continue,
+            };
             let authorized_key = Self {
-                key_type: captures.get(1).unwrap().as_str().trim().to_string(),
-                public_key: captures.get(2).unwrap().as_str().trim().to_string(),
-                comment: captures.get(3).unwrap().as_str().trim().to_string(),
+                key_type: captures.get(1).map(|v| v.as_str()).unwrap_or_default().trim().to_string(),
+                public_key: captures.get(2).map(|v| v.as_str()).unwrap_or_default().trim().to_string(),
+                comment: captures.get(3).map(|v| v.as_str()).unwrap_or_default().trim().to_string(),
             };
             system_authorized_keys.push(authorized_key);
         }
-    
-        Ok(system_authorized_keys)
+
+        system_authorized_keys
     }
 }
 
@@ -55,6 +110,9 @@ fn should_process_authorized_keys_file() {
     let user_info = UserInfo {
         name: "forensicrs".to_string(),
         id: 1,
+        gid: 1000,
+        gecos: String::new(),
+        password: Default::default(),
         home: PathBuf::from("/home/forensicrs"),
         shell: "/bin/bash".to_string(),
         groups: Vec::new(),
@@ -81,3 +139,29 @@ fn should_process_authorized_keys_file() {
         }
     }
 }
+
+#[test]
+fn should_expand_authorized_keys_file_tokens() {
+    let user_info = UserInfo {
+        name: "forensicrs".to_string(),
+        id: 1000,
+        gid: 1000,
+        gecos: String::new(),
+        password: Default::default(),
+        home: PathBuf::from("/home/forensicrs"),
+        shell: "/bin/bash".to_string(),
+        groups: Vec::new(),
+    };
+    assert_eq!(
+        PathBuf::from("/home/forensicrs/.ssh/authorized_keys"),
+        AuthorizedKey::expand_authorized_keys_path(".ssh/authorized_keys", &user_info)
+    );
+    assert_eq!(
+        PathBuf::from("/etc/ssh/authorized_keys/forensicrs-1000"),
+        AuthorizedKey::expand_authorized_keys_path("/etc/ssh/authorized_keys/%u-%U", &user_info)
+    );
+    assert_eq!(
+        PathBuf::from("/home/forensicrs/keys%"),
+        AuthorizedKey::expand_authorized_keys_path("%h/keys%%", &user_info)
+    );
+}
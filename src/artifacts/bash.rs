@@ -62,7 +62,7 @@ impl BashHistory {
     ) -> ForensicResult<Self> {
         let mut bash_history = Self::default();
 
-        let user_home = user_info.home.as_path().join(".bash_history");
+        let user_home = user_info.home.as_path();
 
         bash_history.read_history_timestamps(user_home, fs);
 
@@ -145,6 +145,9 @@ mod bash_tests {
         let user_info = UserInfo {
             name: "forensicrs".to_string(),
             id: 1,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
             home: PathBuf::from("/home/forensicrs"),
             shell: "/bin/bash".to_string(),
             groups: Vec::new(),
@@ -198,6 +201,9 @@ mod bash_tests {
         let user_info = UserInfo {
             name: "forensicrs".to_string(),
             id: 1,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
             home: PathBuf::from("/home/forensicrs"),
             shell: "/bin/bash".to_string(),
             groups: Vec::new(),
@@ -0,0 +1,508 @@
+pub use crate::prelude::{read_rotated_lines, toml::parse_toml, UserInfo};
+pub use crate::ChRootFileSystem;
+use chrono::{DateTime, NaiveDateTime};
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use serde_json::Value;
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+const DOCKER_DAEMON_CONFIG: &str = "/etc/docker/daemon.json";
+const DOCKER_DATA_ROOT: &str = "/var/lib/docker";
+const CONTAINERD_CONFIG: &str = "/etc/containerd/config.toml";
+//API sockets of the container engines, a container that mounts one controls the host
+const DAEMON_SOCKETS: [&str; 4] = ["docker.sock", "containerd.sock", "crio.sock", "podman.sock"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerMount {
+    //path on the host, empty for tmpfs mounts
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub read_write: bool,
+    //bind, volume or tmpfs
+    pub mount_type: String,
+}
+
+//one line of a json-file log driver file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerLogLine {
+    pub timestamp: Option<NaiveDateTime>,
+    //stdout or stderr
+    pub stream: String,
+    pub message: String,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Container {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub image_id: String,
+    pub entrypoint: Vec<String>,
+    pub cmd: Vec<String>,
+    pub created: Option<NaiveDateTime>,
+    pub started: Option<NaiveDateTime>,
+    pub finished: Option<NaiveDateTime>,
+    pub running: bool,
+    pub privileged: bool,
+    pub mounts: Vec<ContainerMount>,
+    pub cap_add: Vec<String>,
+    pub cap_drop: Vec<String>,
+    pub network_mode: String,
+    pub pid_mode: String,
+    pub restart_policy: String,
+    pub path: PathBuf,
+    pub logs: Vec<ContainerLogLine>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DockerDaemonConfig {
+    pub path: PathBuf,
+    //unix://, tcp:// and fd:// addresses the daemon listens on
+    pub hosts: Vec<String>,
+    pub data_root: Option<String>,
+    pub insecure_registries: Vec<String>,
+    pub registry_mirrors: Vec<String>,
+    pub tls_verify: bool,
+    pub userns_remap: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerdConfig {
+    pub path: PathBuf,
+    pub root: Option<String>,
+    pub state: Option<String>,
+    pub grpc_address: Option<String>,
+    //the API without authentication on a tcp port
+    pub grpc_tcp_address: Option<String>,
+    //registries with insecure_skip_verify
+    pub insecure_registries: Vec<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum ContainerFindingKind {
+    #[default]
+    PrivilegedContainer,
+    //the root of the host is mounted inside the container
+    HostRootMount,
+    //the container can talk to the container engine of the host
+    DaemonSocketMount,
+    //the daemon listens on a tcp address
+    ExposedDaemonSocket,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerFinding {
+    pub kind: ContainerFindingKind,
+    //empty for findings of the daemon configuration
+    pub container_id: String,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ContainerInventory {
+    pub daemon_config: Option<DockerDaemonConfig>,
+    pub containerd_config: Option<ContainerdConfig>,
+    pub containers: Vec<Container>,
+    pub findings: Vec<ContainerFinding>,
+}
+
+//a registry of ~/.docker/config.json, the credentials themselves are never kept
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DockerRegistry {
+    pub registry: String,
+    //auth, password or identitytoken stored in the file instead of a credential store
+    pub inline_credentials: bool,
+    pub credential_helper: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DockerClientConfig {
+    pub path: PathBuf,
+    pub registries: Vec<DockerRegistry>,
+    pub creds_store: Option<String>,
+}
+
+impl ContainerInventory {
+    pub fn load_containers(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut inventory = Self {
+            daemon_config: DockerDaemonConfig::load_daemon_config(vfs),
+            containerd_config: ContainerdConfig::load_containerd_config(vfs),
+            ..Default::default()
+        };
+        let data_root = inventory
+            .daemon_config
+            .as_ref()
+            .and_then(|v| v.data_root.clone())
+            .unwrap_or(DOCKER_DATA_ROOT.to_string());
+        let containers_path = Path::new(&data_root).join("containers");
+        let mut entries = vfs.read_dir(&containers_path).unwrap_or_default();
+        entries.sort_by_key(|v| v.to_string());
+        for entry in entries {
+            if let forensic_rs::traits::vfs::VDirEntry::Directory(id) = entry {
+                if let Some(container) = Container::load_container(vfs, &containers_path.join(id)) {
+                    inventory.containers.push(container);
+                }
+            }
+        }
+        inventory.findings = inventory.collect_findings();
+        Ok(inventory)
+    }
+
+    pub fn get_container(&self, name: &str) -> Option<&Container> {
+        self.containers.iter().find(|v| v.name == name || v.id.starts_with(name))
+    }
+
+    fn collect_findings(&self) -> Vec<ContainerFinding> {
+        let mut findings = Vec::new();
+        if let Some(daemon_config) = &self.daemon_config {
+            for host in daemon_config.hosts.iter().filter(|v| v.starts_with("tcp://")) {
+                findings.push(ContainerFinding {
+                    kind: ContainerFindingKind::ExposedDaemonSocket,
+                    container_id: String::new(),
+                    detail: match daemon_config.tls_verify {
+                        true => format!("{} with tlsverify", host),
+                        false => format!("{} without tlsverify", host),
+                    },
+                });
+            }
+        }
+        if let Some(tcp_address) = self.containerd_config.as_ref().and_then(|v| v.grpc_tcp_address.as_ref()) {
+            findings.push(ContainerFinding {
+                kind: ContainerFindingKind::ExposedDaemonSocket,
+                container_id: String::new(),
+                detail: format!("containerd tcp://{}", tcp_address),
+            });
+        }
+        for container in &self.containers {
+            if container.privileged {
+                findings.push(ContainerFinding {
+                    kind: ContainerFindingKind::PrivilegedContainer,
+                    container_id: container.id.clone(),
+                    detail: container.name.clone(),
+                });
+            }
+            for mount in &container.mounts {
+                let kind = if mount.source == Path::new("/") {
+                    ContainerFindingKind::HostRootMount
+                } else if is_daemon_socket(&mount.source) {
+                    ContainerFindingKind::DaemonSocketMount
+                } else {
+                    continue;
+                };
+                findings.push(ContainerFinding {
+                    kind,
+                    container_id: container.id.clone(),
+                    detail: format!("{} -> {}", mount.source.display(), mount.destination.display()),
+                });
+            }
+        }
+        findings
+    }
+
+    pub fn findings_of_kind(&self, kind: ContainerFindingKind) -> Vec<&ContainerFinding> {
+        self.findings.iter().filter(|v| v.kind == kind).collect()
+    }
+}
+
+impl Container {
+    //config.v2.json and hostconfig.json of one directory of /var/lib/docker/containers
+    pub fn load_container(vfs: &mut impl VirtualFileSystem, path: &Path) -> Option<Self> {
+        let config = read_json(vfs, &path.join("config.v2.json"))?;
+        let host_config = read_json(vfs, &path.join("hostconfig.json")).unwrap_or(Value::Null);
+        let id = json_string(&config["ID"]);
+        let mut container = Container {
+            name: json_string(&config["Name"]).trim_start_matches('/').to_string(),
+            image: json_string(&config["Config"]["Image"]),
+            image_id: json_string(&config["Image"]),
+            entrypoint: json_strings(&config["Config"]["Entrypoint"]),
+            cmd: json_strings(&config["Config"]["Cmd"]),
+            created: parse_docker_time(&json_string(&config["Created"])),
+            started: parse_docker_time(&json_string(&config["State"]["StartedAt"])),
+            finished: parse_docker_time(&json_string(&config["State"]["FinishedAt"])),
+            running: config["State"]["Running"].as_bool().unwrap_or(false),
+            privileged: host_config["Privileged"].as_bool().unwrap_or(false),
+            cap_add: json_strings(&host_config["CapAdd"]),
+            cap_drop: json_strings(&host_config["CapDrop"]),
+            network_mode: json_string(&host_config["NetworkMode"]),
+            pid_mode: json_string(&host_config["PidMode"]),
+            restart_policy: json_string(&host_config["RestartPolicy"]["Name"]),
+            path: path.to_path_buf(),
+            ..Default::default()
+        };
+        if let Some(mount_points) = config["MountPoints"].as_object() {
+            for mount_point in mount_points.values() {
+                container.mounts.push(ContainerMount {
+                    source: PathBuf::from(json_string(&mount_point["Source"])),
+                    destination: PathBuf::from(json_string(&mount_point["Destination"])),
+                    read_write: mount_point["RW"].as_bool().unwrap_or(false),
+                    mount_type: json_string(&mount_point["Type"]),
+                });
+            }
+        }
+        //"source:destination[:options]" binds of containers that never started have no mount point yet
+        for bind in json_strings(&host_config["Binds"]) {
+            let columns: Vec<&str> = bind.split(':').collect();
+            if columns.len() < 2 || container.mounts.iter().any(|v| v.destination == Path::new(columns[1])) {
+                continue;
+            }
+            container.mounts.push(ContainerMount {
+                source: PathBuf::from(columns[0]),
+                destination: PathBuf::from(columns[1]),
+                read_write: !columns.get(2).is_some_and(|v| v.split(',').any(|option| option == "ro")),
+                mount_type: "bind".to_string(),
+            });
+        }
+        container.mounts.sort_by(|a, b| a.destination.cmp(&b.destination));
+
+        let log_path = match json_string(&config["LogPath"]) {
+            v if v.is_empty() => path.join(format!("{}-json.log", id)),
+            v => PathBuf::from(v),
+        };
+        container.logs = load_json_logs(vfs, &log_path);
+        container.id = id;
+        Some(container)
+    }
+}
+
+impl DockerDaemonConfig {
+    pub fn load_daemon_config(vfs: &mut impl VirtualFileSystem) -> Option<Self> {
+        let config = read_json(vfs, Path::new(DOCKER_DAEMON_CONFIG))?;
+        //"graph" is the name data-root had before docker 17.05
+        let data_root = [&config["data-root"], &config["graph"]]
+            .iter()
+            .map(|v| json_string(v))
+            .find(|v| !v.is_empty());
+        let userns_remap = Some(json_string(&config["userns-remap"])).filter(|v| !v.is_empty());
+        Some(DockerDaemonConfig {
+            path: PathBuf::from(DOCKER_DAEMON_CONFIG),
+            hosts: json_strings(&config["hosts"]),
+            data_root,
+            insecure_registries: json_strings(&config["insecure-registries"]),
+            registry_mirrors: json_strings(&config["registry-mirrors"]),
+            tls_verify: config["tlsverify"].as_bool().unwrap_or(false),
+            userns_remap,
+        })
+    }
+}
+
+impl ContainerdConfig {
+    pub fn load_containerd_config(vfs: &mut impl VirtualFileSystem) -> Option<Self> {
+        let contents = vfs.read_to_string(Path::new(CONTAINERD_CONFIG)).ok()?;
+        let config = parse_toml(&contents)?;
+        let value = |value: &Value| Some(json_string(value)).filter(|v| !v.is_empty());
+        //registries are configured in [plugins."<cri plugin>".registry.configs."<host>".tls], the name of the cri
+        //plugin changes between versions of containerd
+        let mut insecure_registries: Vec<String> = Vec::new();
+        for plugin in config["plugins"].as_object().into_iter().flat_map(|v| v.values()) {
+            for (registry, registry_config) in plugin["registry"]["configs"].as_object().into_iter().flatten() {
+                if registry_config["tls"]["insecure_skip_verify"].as_bool() == Some(true) {
+                    insecure_registries.push(registry.clone());
+                }
+            }
+        }
+        insecure_registries.sort();
+        Some(ContainerdConfig {
+            path: PathBuf::from(CONTAINERD_CONFIG),
+            root: value(&config["root"]),
+            state: value(&config["state"]),
+            grpc_address: value(&config["grpc"]["address"]),
+            grpc_tcp_address: value(&config["grpc"]["tcp_address"]),
+            insecure_registries,
+        })
+    }
+}
+
+impl DockerClientConfig {
+    pub fn load_docker_client_config(
+        user_info: UserInfo,
+        vfs: &mut impl VirtualFileSystem,
+    ) -> ForensicResult<Option<Self>> {
+        let path = user_info.home.join(".docker/config.json");
+        let config = match read_json(vfs, &path) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let mut client_config = DockerClientConfig {
+            path,
+            creds_store: Some(json_string(&config["credsStore"])).filter(|v| !v.is_empty()),
+            ..Default::default()
+        };
+        if let Some(auths) = config["auths"].as_object() {
+            for (registry, auth) in auths {
+                let inline_credentials = ["auth", "password", "identitytoken"]
+                    .iter()
+                    .any(|v| !json_string(&auth[v]).is_empty());
+                client_config.registries.push(DockerRegistry {
+                    registry: registry.clone(),
+                    inline_credentials,
+                    credential_helper: None,
+                });
+            }
+        }
+        if let Some(cred_helpers) = config["credHelpers"].as_object() {
+            for (registry, helper) in cred_helpers {
+                match client_config.registries.iter_mut().find(|v| &v.registry == registry) {
+                    Some(v) => v.credential_helper = Some(json_string(helper)),
+                    None => client_config.registries.push(DockerRegistry {
+                        registry: registry.clone(),
+                        inline_credentials: false,
+                        credential_helper: Some(json_string(helper)),
+                    }),
+                }
+            }
+        }
+        client_config.registries.sort_by(|a, b| a.registry.cmp(&b.registry));
+        Ok(Some(client_config))
+    }
+}
+
+//the json-file driver rotates to -json.log.1, -json.log.2 and so on
+fn load_json_logs(vfs: &mut impl VirtualFileSystem, log_path: &Path) -> Vec<ContainerLogLine> {
+    let mut logs = Vec::new();
+    for rotated_line in read_rotated_lines(vfs, log_path) {
+        let entry: Value = match serde_json::from_str(&rotated_line.line) {
+            Ok(v) => v,
+            Err(_e) => continue,
+        };
+        logs.push(ContainerLogLine {
+            timestamp: parse_docker_time(&json_string(&entry["time"])),
+            stream: json_string(&entry["stream"]),
+            message: json_string(&entry["log"]).trim_end_matches('\n').to_string(),
+            path: rotated_line.path,
+            line_number: rotated_line.line_number,
+        });
+    }
+    logs
+}
+
+fn read_json(vfs: &mut impl VirtualFileSystem, path: &Path) -> Option<Value> {
+    let contents = match vfs.read_to_string(path) {
+        Ok(v) => v,
+        Err(_e) => return None,
+    };
+    serde_json::from_str(&contents).ok()
+}
+
+fn json_string(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+//null, missing and non string values give an empty list
+fn json_strings(value: &Value) -> Vec<String> {
+    match value.as_array() {
+        Some(v) => v.iter().filter_map(|v| v.as_str()).map(|v| v.to_string()).collect(),
+        None => Vec::new(),
+    }
+}
+
+//RFC 3339 with nanoseconds, the zero time 0001-01-01 stands for never
+fn parse_docker_time(value: &str) -> Option<NaiveDateTime> {
+    let timestamp = DateTime::parse_from_rfc3339(value).ok()?.naive_utc();
+    if timestamp.and_utc().timestamp() <= 0 {
+        return None;
+    }
+    Some(timestamp)
+}
+
+fn is_daemon_socket(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|v| DAEMON_SOCKETS.contains(&v.to_string_lossy().as_ref()))
+}
+
+#[cfg(test)]
+mod containers_tests {
+    use super::*;
+    use crate::prelude::UserInfo;
+
+    #[test]
+    fn should_load_docker_containers() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let inventory = ContainerInventory::load_containers(&mut vfs).expect("Should load containers");
+
+        let daemon_config = inventory.daemon_config.as_ref().unwrap();
+        assert_eq!(vec!["10.0.0.5:5000"], daemon_config.insecure_registries);
+        assert!(!daemon_config.tls_verify);
+        assert_eq!(2, inventory.containers.len());
+
+        let web = inventory.get_container("web").unwrap();
+        assert_eq!("nginx:1.25", web.image);
+        assert_eq!(vec!["/docker-entrypoint.sh"], web.entrypoint);
+        assert_eq!(vec!["nginx", "-g", "daemon off;"], web.cmd);
+        assert!(web.running);
+        assert_eq!(None, web.finished);
+        assert_eq!("2024-03-01 10:00:05.123456789", web.started.unwrap().to_string());
+        assert_eq!("unless-stopped", web.restart_policy);
+        assert_eq!(vec!["NET_RAW"], web.cap_drop);
+        assert_eq!(1, web.mounts.len());
+        assert!(!web.mounts[0].read_write);
+        //rotated log first, the line that is not json is skipped
+        let messages: Vec<&str> = web.logs.iter().map(|v| v.message.as_str()).collect();
+        assert_eq!(3, messages.len());
+        assert!(messages[0].contains("GET / HTTP/1.1"));
+        assert_eq!("stderr", web.logs[2].stream);
+
+        let debug = inventory.get_container("9a1b2c3d").unwrap();
+        assert_eq!("debug", debug.name);
+        assert!(debug.privileged);
+        assert_eq!("host", debug.network_mode);
+        assert_eq!(vec!["SYS_ADMIN", "SYS_PTRACE"], debug.cap_add);
+        assert_eq!(3, debug.mounts.len());
+        assert!(!debug.mounts[1].read_write);
+
+        let containerd_config = inventory.containerd_config.as_ref().unwrap();
+        assert_eq!(Some("/var/lib/containerd"), containerd_config.root.as_deref());
+        assert_eq!(Some("/run/containerd/containerd.sock"), containerd_config.grpc_address.as_deref());
+        assert_eq!(vec!["registry.internal:5000"], containerd_config.insecure_registries);
+
+        let findings: Vec<(ContainerFindingKind, &str)> =
+            inventory.findings.iter().map(|v| (v.kind.clone(), v.detail.as_str())).collect();
+        assert_eq!(
+            vec![
+                (ContainerFindingKind::ExposedDaemonSocket, "tcp://0.0.0.0:2375 without tlsverify"),
+                (ContainerFindingKind::ExposedDaemonSocket, "containerd tcp://0.0.0.0:10010"),
+                (ContainerFindingKind::PrivilegedContainer, "debug"),
+                (ContainerFindingKind::HostRootMount, "/ -> /host"),
+                (ContainerFindingKind::DaemonSocketMount, "/run/containerd/containerd.sock -> /run/containerd/containerd.sock"),
+                (ContainerFindingKind::DaemonSocketMount, "/var/run/docker.sock -> /var/run/docker.sock"),
+            ],
+            findings
+        );
+    }
+
+    #[test]
+    fn should_keep_only_registries_of_docker_client_config() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let user_info = UserInfo::get_user_info("forensicrs".to_string(), &mut vfs).unwrap();
+        let client_config = DockerClientConfig::load_docker_client_config(user_info, &mut vfs).unwrap().unwrap();
+
+        assert_eq!(Some("desktop"), client_config.creds_store.as_deref());
+        let registries: Vec<(&str, bool)> =
+            client_config.registries.iter().map(|v| (v.registry.as_str(), v.inline_credentials)).collect();
+        assert_eq!(
+            vec![
+                ("10.0.0.5:5000", true),
+                ("123456789012.dkr.ecr.us-east-1.amazonaws.com", false),
+                ("https://index.docker.io/v1/", true),
+                ("registry.example.com", false),
+            ],
+            registries
+        );
+        assert_eq!(Some("ecr-login"), client_config.registries[1].credential_helper.as_deref());
+        assert!(!format!("{:?}", client_config).contains("Zm9yZW5zaWNyczpodW50ZXIy"));
+    }
+}
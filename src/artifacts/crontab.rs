@@ -1,5 +1,5 @@
 pub use crate::ChRootFileSystem;
-pub use crate::prelude::{UserInfo, group::SystemGroups};
+pub use crate::prelude::{UserInfo, group::SystemGroups, packages::PackageInventory};
 pub use forensic_rs::{
     core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
 };
@@ -25,6 +25,23 @@ pub struct CrontabTask {
     pub username: String,
     pub command: String,
     pub schedule: CrontabSchedule,
+    //crontab file the task was read from
+    pub source: PathBuf,
+    pub package: Option<String>,
+    //crontab files or programs not installed by any package
+    pub highlighted: bool,
+}
+
+impl CrontabTask {
+    //the crontab file and the program the command starts are checked against the package database
+    pub fn tag_package(&mut self, package_inventory: &PackageInventory) {
+        self.package = package_inventory.owner(&self.source).map(|v| v.to_string());
+        let program_orphaned = match self.command.split_whitespace().next() {
+            Some(program) if program.starts_with('/') => package_inventory.is_orphaned(Path::new(program)),
+            _ => false,
+        };
+        self.highlighted = package_inventory.is_orphaned(&self.source) || program_orphaned;
+    }
 }
 
 lazy_static! {
@@ -41,6 +58,7 @@ impl CrontabSchedule {
         &mut self,
         vfs: &mut impl VirtualFileSystem,
         username: String,
+        package_inventory: &PackageInventory,
     ) -> ForensicResult<Vec<CrontabTask>> {
         let crontab_paths = Self::get_crontab_files(username);
         let mut crontab_tasks: Vec<CrontabTask> = Vec::new();
@@ -72,11 +90,14 @@ impl CrontabSchedule {
                         None => "no command",
                     };
 
-                    let crontab_task = CrontabTask {
+                    let mut crontab_task = CrontabTask {
                         username: "root".to_string(),
                         command: crontab_command.trim().to_string(),
                         schedule: crontab_schedule,
+                        source: path.clone(),
+                        ..Default::default()
                     };
+                    crontab_task.tag_package(package_inventory);
 
                     crontab_tasks.push(crontab_task);
                     continue;
@@ -87,11 +108,14 @@ impl CrontabSchedule {
                     None => "no command",
                 };
 
-                let crontab_task = CrontabTask {
+                let mut crontab_task = CrontabTask {
                     username: crontab_columns[5].to_string(),
                     command: crontab_command.trim().to_string(),
                     schedule: crontab_schedule,
+                    source: path.clone(),
+                    ..Default::default()
                 };
+                crontab_task.tag_package(package_inventory);
 
                 crontab_tasks.push(crontab_task);
             }
@@ -135,16 +159,21 @@ fn should_process_crontab_file() {
     let user_info = UserInfo {
         name: "forensicrs".to_string(),
         id: 1,
+        gid: 1000,
+        gecos: String::new(),
+        password: Default::default(),
         home: PathBuf::from("/home/forensicrs"),
         shell: "/bin/bash".to_string(),
         groups: Vec::new(),
     };
 
+    let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
     let mut crontab_schedule = CrontabSchedule::default();
     let crontab_tasks = CrontabSchedule::process_crontab_files(
         &mut crontab_schedule,
         &mut vfs,
         user_info.name,
+        &package_inventory,
     );
 
     match crontab_tasks {
@@ -159,7 +188,10 @@ fn should_process_crontab_file() {
             let crontab_task = CrontabTask {
                 username: "root".to_string(),
                 command: "cd / && run-parts --report /etc/cron.hourly".to_string(),
-                schedule: crontab_schedule
+                schedule: crontab_schedule,
+                source: PathBuf::from("/etc/crontab"),
+                package: None,
+                highlighted: true,
             };
             assert_eq!(crontab_task, task[0]);
         },
@@ -168,3 +200,29 @@ fn should_process_crontab_file() {
         }
     }
 }
+
+#[test]
+fn should_tag_crontab_tasks_with_their_package() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(StdVirtualFS::new()));
+    let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
+
+    let mut packaged = CrontabTask {
+        command: "/usr/sbin/rsyslogd -n".to_string(),
+        source: PathBuf::from("/etc/rsyslog.conf"),
+        ..Default::default()
+    };
+    packaged.tag_package(&package_inventory);
+    assert_eq!(Some("rsyslog".to_string()), packaged.package);
+    assert!(!packaged.highlighted);
+
+    //packaged crontab running a dropped program
+    let mut dropped = CrontabTask {
+        command: "/usr/bin/.sysd --daemon".to_string(),
+        ..packaged.clone()
+    };
+    dropped.tag_package(&package_inventory);
+    assert!(dropped.highlighted);
+}
@@ -0,0 +1,192 @@
+pub use crate::prelude::{list_files, UserInfo};
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum SessionScriptKind {
+    //~/.xinitrc or /etc/X11/xinit/xinitrc, run by startx
+    #[default]
+    Xinitrc,
+    //~/.xsession, run by the display manager instead of the default session
+    Xsession,
+    //~/.xprofile, sourced by most display managers
+    Xprofile,
+    //scripts of ~/.config/autostart-scripts and ~/.config/plasma-workspace/env
+    KdeAutostart,
+}
+
+//[Desktop Entry] of an autostart .desktop file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DesktopEntry {
+    pub path: PathBuf,
+    pub name: String,
+    pub exec: String,
+    pub hidden: bool,
+    pub autostart_enabled: Option<bool>,
+    pub only_show_in: Vec<String>,
+    //Hidden=true or X-GNOME-Autostart-enabled=false stop the entry from running
+    pub enabled: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SessionScript {
+    pub path: PathBuf,
+    pub kind: SessionScriptKind,
+    pub contents: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DesktopPersistence {
+    pub autostart_entries: Vec<DesktopEntry>,
+    pub session_scripts: Vec<SessionScript>,
+}
+
+impl DesktopPersistence {
+    pub fn load_user_desktop_persistence(
+        user_info: UserInfo,
+        vfs: &mut impl VirtualFileSystem,
+    ) -> ForensicResult<Self> {
+        let mut desktop_persistence = Self::default();
+        let home = user_info.home;
+        desktop_persistence.process_autostart_dir(vfs, &home.join(".config/autostart"));
+        for (file_name, kind) in [
+            (".xinitrc", SessionScriptKind::Xinitrc),
+            (".xsession", SessionScriptKind::Xsession),
+            (".xsessionrc", SessionScriptKind::Xsession),
+            (".xprofile", SessionScriptKind::Xprofile),
+        ] {
+            desktop_persistence.add_session_script(vfs, &home.join(file_name), kind);
+        }
+        for directory in [".config/autostart-scripts", ".config/plasma-workspace/env", ".kde/Autostart"] {
+            for path in list_files(vfs, &home.join(directory), 0) {
+                desktop_persistence.add_session_script(vfs, &path, SessionScriptKind::KdeAutostart);
+            }
+        }
+        Ok(desktop_persistence)
+    }
+
+    pub fn load_system_desktop_persistence(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut desktop_persistence = Self::default();
+        desktop_persistence.process_autostart_dir(vfs, Path::new("/etc/xdg/autostart"));
+        desktop_persistence.add_session_script(vfs, Path::new("/etc/X11/xinit/xinitrc"), SessionScriptKind::Xinitrc);
+        Ok(desktop_persistence)
+    }
+
+    pub fn enabled_entries(&self) -> Vec<&DesktopEntry> {
+        self.autostart_entries.iter().filter(|v| v.enabled).collect()
+    }
+
+    fn process_autostart_dir(&mut self, vfs: &mut impl VirtualFileSystem, directory: &Path) {
+        for path in list_files(vfs, directory, 0) {
+            if path.extension().unwrap_or_default() != "desktop" {
+                continue;
+            }
+            if let Ok(contents) = vfs.read_to_string(&path) {
+                let mut desktop_entry = DesktopEntry::parse(&contents);
+                desktop_entry.path = path;
+                self.autostart_entries.push(desktop_entry);
+            }
+        }
+    }
+
+    fn add_session_script(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path, kind: SessionScriptKind) {
+        if let Ok(contents) = vfs.read_to_string(path) {
+            self.session_scripts.push(SessionScript {
+                path: path.to_path_buf(),
+                kind,
+                contents,
+            });
+        }
+    }
+}
+
+impl DesktopEntry {
+    //only the keys of the [Desktop Entry] group, the localized Name[xx] keys are ignored
+    pub fn parse(contents: &str) -> Self {
+        let mut desktop_entry = Self::default();
+        let mut in_desktop_entry = false;
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('[') {
+                in_desktop_entry = line == "[Desktop Entry]";
+                continue;
+            }
+            if !in_desktop_entry || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "Name" => desktop_entry.name = value.to_string(),
+                "Exec" => desktop_entry.exec = value.to_string(),
+                "Hidden" => desktop_entry.hidden = value.eq_ignore_ascii_case("true"),
+                "X-GNOME-Autostart-enabled" => {
+                    desktop_entry.autostart_enabled = Some(value.eq_ignore_ascii_case("true"))
+                }
+                "OnlyShowIn" => {
+                    desktop_entry.only_show_in = value
+                        .split(';')
+                        .filter(|v| !v.is_empty())
+                        .map(|v| v.to_string())
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+        desktop_entry.enabled = !desktop_entry.hidden && desktop_entry.autostart_enabled != Some(false);
+        desktop_entry
+    }
+}
+
+#[cfg(test)]
+mod desktop_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_desktop_persistence() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let user_info = UserInfo {
+            name: "forensicrs".to_string(),
+            home: PathBuf::from("/home/forensicrs"),
+            ..Default::default()
+        };
+
+        let user_persistence = DesktopPersistence::load_user_desktop_persistence(user_info, &mut vfs)
+            .expect("Should load user desktop persistence");
+        let entry_test = DesktopEntry {
+            path: PathBuf::from("/home/forensicrs/.config/autostart/tracker-miner.desktop"),
+            name: "Tracker File System Miner".to_string(),
+            exec: "/home/forensicrs/.local/share/.tracker/miner --daemon".to_string(),
+            hidden: false,
+            autostart_enabled: Some(true),
+            only_show_in: Vec::new(),
+            enabled: true,
+        };
+        assert_eq!(vec![entry_test], user_persistence.autostart_entries);
+        assert_eq!(1, user_persistence.session_scripts.len());
+        assert_eq!(SessionScriptKind::Xprofile, user_persistence.session_scripts[0].kind);
+
+        let system_persistence = DesktopPersistence::load_system_desktop_persistence(&mut vfs)
+            .expect("Should load system desktop persistence");
+        assert_eq!(2, system_persistence.autostart_entries.len());
+        let enabled: Vec<&str> = system_persistence.enabled_entries().iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["Print Queue Applet"], enabled);
+        assert_eq!(
+            vec!["GNOME".to_string(), "Unity".to_string()],
+            system_persistence.autostart_entries[0].only_show_in
+        );
+    }
+}
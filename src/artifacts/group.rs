@@ -6,11 +6,16 @@ pub use std::{
 pub use crate::ChRootFileSystem;
 pub use crate::prelude::UserInfo;
 
+//groups that grant administrative access or read access to sensitive data
+pub const PRIVILEGED_GROUPS: [&str; 7] = ["sudo", "wheel", "adm", "docker", "lxd", "disk", "shadow"];
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Group {
     pub name: String,
     pub group_id: u32,
-    pub users: Vec<String>
+    pub users: Vec<String>,
+    pub administrators: Vec<String>,
+    pub privileged: bool,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
@@ -20,10 +25,17 @@ pub struct SystemGroups {
 
 impl SystemGroups {
 
-    pub fn get_groups_for_user(&self, username: &str) -> ForensicResult<Vec<Group>> {
-        let mut groups = Vec::new();
+    //primary group (GID of /etc/passwd) first, then the groups that list the user as member
+    pub fn get_groups_for_user(&self, username: &str, primary_gid: u32) -> ForensicResult<Vec<Group>> {
+        let mut groups: Vec<Group> = self
+            .groups
+            .iter()
+            .filter(|group| group.group_id == primary_gid)
+            .take(1)
+            .cloned()
+            .collect();
         for group in &self.groups {
-            if group.users.contains(&username.to_string()) {
+            if group.users.contains(&username.to_string()) && !groups.contains(group) {
                 groups.push(group.clone());
             }
         }
@@ -39,30 +51,71 @@ impl SystemGroups {
         
         for group_line in reader_groups.lines() {
             let group_line = group_line?;
-            let group_columns: Vec<&str> = group_line.split(":").collect();
-            if group_columns.len() < 2 {
+            let group_columns: Vec<&str> = group_line.split(':').collect();
+            //malformed lines and NIS entries without a numeric GID are skipped
+            if group_columns.len() < 4 {
                 continue;
             }
-    
-            let mut users: Vec<String> = Vec::new();
-            for member in group_columns[3].split(",") {
-                users.push(member.trim().to_string());
-            }
+            let group_id = match group_columns[2].trim().parse::<u32>() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+            let name = group_columns[0].trim().to_string();
     
             let group = Group {
-                name: group_columns[0].trim().to_string(),
-                group_id: group_columns[2].trim().parse::<u32>().unwrap(),
-                users
+                privileged: PRIVILEGED_GROUPS.contains(&name.as_str()),
+                name,
+                group_id,
+                users: split_members(group_columns[3]),
+                administrators: Vec::new(),
             };
     
             system_groups.push(group);
         }
-    
-        Ok(Self {
+
+        let mut system_groups = Self {
             groups: system_groups
-        })
+        };
+        system_groups.merge_gshadow_file(vfs);
+        Ok(system_groups)
     
     }
+
+    //adds the administrators and members of /etc/gshadow (name:password:admins:members)
+    pub fn merge_gshadow_file(&mut self, vfs: &mut impl VirtualFileSystem) {
+        let gshadow = match vfs.read_to_string(Path::new("/etc/gshadow")) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        for gshadow_line in gshadow.lines() {
+            let gshadow_columns: Vec<&str> = gshadow_line.split(':').collect();
+            if gshadow_columns.len() < 4 {
+                continue;
+            }
+            let group = match self.groups.iter_mut().find(|v| v.name == gshadow_columns[0].trim()) {
+                Some(v) => v,
+                None => continue,
+            };
+            for administrator in split_members(gshadow_columns[2]) {
+                if !group.administrators.contains(&administrator) {
+                    group.administrators.push(administrator);
+                }
+            }
+            for member in split_members(gshadow_columns[3]) {
+                if !group.users.contains(&member) {
+                    group.users.push(member);
+                }
+            }
+        }
+    }
+}
+
+fn split_members(members: &str) -> Vec<String> {
+    members
+        .split(',')
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
 }
 
 #[test]
@@ -79,13 +132,17 @@ fn should_process_group_file() {
             let root_group = Group {
                 name: "root".to_string(),
                 group_id: 0,
-                users: vec!["".to_string()],
+                users: Vec::new(),
+                administrators: Vec::new(),
+                privileged: false,
             };
             let adm_group_users = vec!["syslog".to_string(), "forensicrs".to_string()];
             let adm_group = Group {
                 name: "adm".to_string(),
                 group_id: 4,
                 users: adm_group_users,
+                administrators: Vec::new(),
+                privileged: true,
             };
             assert_eq!(root_group, groups.groups[0]);
             assert_eq!(adm_group, groups.groups[4]);
@@ -94,4 +151,25 @@ fn should_process_group_file() {
             panic!("Error getting groups: {:?}", e);
         }
     }
-}
\ No newline at end of file
+}
+#[test]
+fn should_resolve_primary_and_gshadow_groups() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    let system_groups = SystemGroups::process_group_file(&mut vfs).expect("Should process groups");
+
+    let root_groups = system_groups.get_groups_for_user("root", 0).expect("Should get groups");
+    let root_group_names: Vec<&str> = root_groups.iter().map(|v| v.name.as_str()).collect();
+    assert_eq!(vec!["root"], root_group_names);
+
+    let user_groups = system_groups.get_groups_for_user("forensicrs", 1000).expect("Should get groups");
+    assert_eq!("forensicrs", user_groups[0].name);
+    assert!(user_groups.iter().any(|v| v.name == "sudo" && v.privileged));
+
+    let docker_group = system_groups.groups.iter().find(|v| v.name == "docker").unwrap();
+    assert_eq!(vec!["forensicrs".to_string()], docker_group.administrators);
+    assert_eq!(vec!["svc-update".to_string()], docker_group.users);
+}
@@ -0,0 +1,224 @@
+pub use crate::prelude::packages::{DigestAlgorithm, PackageInventory};
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+pub use std::{
+    collections::HashMap,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//directories where a replaced or dropped binary is a finding, /usr/sbin holds sshd
+const VERIFIED_DIRS: [&str; 5] = ["/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/lib"];
+//merged /usr systems install /bin/x as /usr/bin/x and the other way around
+const MERGED_USR_DIRS: [(&str, &str); 3] = [("/bin", "/usr/bin"), ("/sbin", "/usr/sbin"), ("/lib", "/usr/lib")];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum IntegrityStatus {
+    //the hash of the file differs from the package database
+    #[default]
+    Modified,
+    //a packaged file is not in the evidence
+    Missing,
+    //an executable of the verified directories that no package installs
+    Unexpected,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntegrityFinding {
+    pub status: IntegrityStatus,
+    pub path: PathBuf,
+    pub package: Option<String>,
+    pub algorithm: Option<DigestAlgorithm>,
+    pub expected_digest: Option<String>,
+    pub actual_digest: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IntegrityReport {
+    //packaged files of the verified directories compared with their digest
+    pub verified_files: usize,
+    //digests with an algorithm that cannot be computed
+    pub unverifiable_files: usize,
+    pub findings: Vec<IntegrityFinding>,
+}
+
+impl IntegrityReport {
+    //debsums like check of the package digests, no file of the evidence is trusted
+    pub fn verify_packages(
+        inventory: &PackageInventory,
+        vfs: &mut impl VirtualFileSystem,
+    ) -> ForensicResult<Self> {
+        let mut report = Self::default();
+        //the same file can be listed by several packages or databases
+        let mut computed_digests: HashMap<(PathBuf, String), Option<String>> = HashMap::new();
+
+        for package in &inventory.packages {
+            for file_digest in &package.digests {
+                if !is_verified_path(&file_digest.path) {
+                    continue;
+                }
+                if matches!(file_digest.algorithm, DigestAlgorithm::Unknown(_)) {
+                    report.unverifiable_files += 1;
+                    continue;
+                }
+                report.verified_files += 1;
+                let cache_key = (file_digest.path.clone(), format!("{:?}", file_digest.algorithm));
+                let actual_digest = computed_digests
+                    .entry(cache_key)
+                    .or_insert_with(|| {
+                        read_with_merged_usr(vfs, &file_digest.path)
+                            .and_then(|contents| hash_contents(&file_digest.algorithm, &contents))
+                    })
+                    .clone();
+                let status = match actual_digest.as_deref() {
+                    None => IntegrityStatus::Missing,
+                    Some(v) if v.eq_ignore_ascii_case(&file_digest.digest) => continue,
+                    Some(_) => IntegrityStatus::Modified,
+                };
+                report.findings.push(IntegrityFinding {
+                    status,
+                    path: file_digest.path.clone(),
+                    package: Some(package.name.clone()),
+                    algorithm: Some(file_digest.algorithm.clone()),
+                    expected_digest: Some(file_digest.digest.clone()),
+                    actual_digest,
+                });
+            }
+        }
+
+        //without a package database every file would be unexpected
+        if inventory.has_packages() {
+            let mut files = Vec::new();
+            for directory in VERIFIED_DIRS {
+                walk_files(vfs, Path::new(directory), &mut files);
+            }
+            for path in files {
+                let packaged = inventory.file_owners.contains_key(&path)
+                    || merged_usr_alias(&path).is_some_and(|v| inventory.file_owners.contains_key(&v));
+                if !packaged && is_executable(vfs, &path) {
+                    report.findings.push(IntegrityFinding {
+                        status: IntegrityStatus::Unexpected,
+                        path,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn findings_with_status(&self, status: IntegrityStatus) -> Vec<&IntegrityFinding> {
+        self.findings.iter().filter(|v| v.status == status).collect()
+    }
+}
+
+fn is_verified_path(path: &Path) -> bool {
+    VERIFIED_DIRS.iter().any(|v| path.starts_with(v))
+}
+
+fn hash_contents(algorithm: &DigestAlgorithm, contents: &[u8]) -> Option<String> {
+    let digest = match algorithm {
+        DigestAlgorithm::Md5 => Md5::digest(contents).to_vec(),
+        DigestAlgorithm::Sha1 => Sha1::digest(contents).to_vec(),
+        DigestAlgorithm::Sha256 => Sha256::digest(contents).to_vec(),
+        DigestAlgorithm::Sha384 => Sha384::digest(contents).to_vec(),
+        DigestAlgorithm::Sha512 => Sha512::digest(contents).to_vec(),
+        DigestAlgorithm::Unknown(_) => return None,
+    };
+    Some(digest.iter().map(|v| format!("{:02x}", v)).collect())
+}
+
+fn merged_usr_alias(path: &Path) -> Option<PathBuf> {
+    MERGED_USR_DIRS.iter().find_map(|(root_dir, usr_dir)| {
+        if let Ok(v) = path.strip_prefix(root_dir) {
+            return Some(Path::new(usr_dir).join(v));
+        }
+        path.strip_prefix(usr_dir).ok().map(|v| Path::new(root_dir).join(v))
+    })
+}
+
+fn read_with_merged_usr(vfs: &mut impl VirtualFileSystem, path: &Path) -> Option<Vec<u8>> {
+    if let Ok(v) = vfs.read_all(path) {
+        return Some(v);
+    }
+    vfs.read_all(&merged_usr_alias(path)?).ok()
+}
+
+//the metadata of the vfs has no permission bits, binaries are told by the ELF magic and scripts by the interpreter line
+fn is_executable(vfs: &mut impl VirtualFileSystem, path: &Path) -> bool {
+    let mut magic = [0u8; 4];
+    match vfs.read(path, 0, &mut magic) {
+        Ok(v) => magic[..v] == *b"\x7fELF" || magic[..v].starts_with(b"#!"),
+        Err(_e) => false,
+    }
+}
+
+//regular files only, links of update-alternatives point to packaged files
+fn walk_files(vfs: &mut impl VirtualFileSystem, directory: &Path, files: &mut Vec<PathBuf>) {
+    let mut entries = match vfs.read_dir(directory) {
+        Ok(v) => v,
+        Err(_e) => return,
+    };
+    entries.sort_by_key(|v| v.to_string());
+    for entry in entries {
+        match entry {
+            forensic_rs::traits::vfs::VDirEntry::Directory(name) => walk_files(vfs, &directory.join(name), files),
+            forensic_rs::traits::vfs::VDirEntry::File(name) => files.push(directory.join(name)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod integrity_tests {
+    use super::*;
+
+    #[test]
+    fn should_verify_packaged_binaries() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let inventory = PackageInventory::load_packages(&mut vfs).expect("Should load packages");
+        let report = IntegrityReport::verify_packages(&inventory, &mut vfs).expect("Should verify packages");
+
+        let modified: Vec<(&Path, Option<&str>)> = report
+            .findings_with_status(IntegrityStatus::Modified)
+            .iter()
+            .map(|v| (v.path.as_path(), v.package.as_deref()))
+            .collect();
+        assert_eq!(
+            vec![
+                (Path::new("/usr/bin/ls"), Some("coreutils")),
+                (Path::new("/usr/bin/ls"), Some("coreutils-single")),
+            ],
+            modified
+        );
+        let missing: Vec<&Path> = report
+            .findings_with_status(IntegrityStatus::Missing)
+            .iter()
+            .map(|v| v.path.as_path())
+            .collect();
+        assert_eq!(
+            vec![
+                Path::new("/bin/cat"),
+                Path::new("/usr/sbin/rsyslogd"),
+                Path::new("/usr/bin/ps"),
+                Path::new("/usr/bin/top"),
+            ],
+            missing
+        );
+        let unexpected = report.findings_with_status(IntegrityStatus::Unexpected);
+        //data files dropped in the verified directories are not reported
+        let unexpected: Vec<&Path> = unexpected.iter().map(|v| v.path.as_path()).collect();
+        assert_eq!(vec![Path::new("/usr/bin/.sysd"), Path::new("/usr/sbin/update-cache")], unexpected);
+        assert_eq!(9, report.verified_files);
+    }
+}
@@ -0,0 +1,272 @@
+pub use crate::prelude::glob_files;
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    collections::HashSet,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+const MODULES_LOAD_DIRS: [&str; 2] = ["/etc/modules-load.d", "/usr/lib/modules-load.d"];
+const MODPROBE_DIRS: [&str; 3] = ["/etc/modprobe.d", "/usr/lib/modprobe.d", "/lib/modprobe.d"];
+const MODULES_DIRS: [&str; 2] = ["/lib/modules", "/usr/lib/modules"];
+const MODULE_EXTENSIONS: [&str; 4] = [".ko", ".ko.xz", ".ko.gz", ".ko.zst"];
+//install commands used to disable a module instead of running something
+const DISABLING_COMMANDS: [&str; 6] = ["/bin/true", "/bin/false", "/usr/bin/true", "/usr/bin/false", "true", "false"];
+
+//module loaded at boot by /etc/modules or a modules-load.d file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ModuleLoadEntry {
+    pub module: String,
+    pub arguments: Vec<String>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum ModprobeCommand {
+    #[default]
+    Install,
+    Remove,
+    Blacklist,
+    Alias,
+    Options,
+    Softdep,
+    Unknown(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ModprobeDirective {
+    pub command: ModprobeCommand,
+    //module name, or the alias wildcard for alias directives
+    pub module: String,
+    pub arguments: String,
+    //install and remove directives that run something else than true or false
+    pub runs_command: bool,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KernelModuleFile {
+    pub kernel_version: String,
+    pub path: PathBuf,
+    //None when the kernel has no modules.dep to compare with
+    pub listed_in_dep: Option<bool>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct KernelModules {
+    pub load_entries: Vec<ModuleLoadEntry>,
+    pub modprobe_directives: Vec<ModprobeDirective>,
+    pub module_files: Vec<KernelModuleFile>,
+}
+
+impl KernelModules {
+    pub fn load_kernel_modules(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut kernel_modules = Self::default();
+        kernel_modules.process_modules_file(vfs, Path::new("/etc/modules"));
+        for directory in MODULES_LOAD_DIRS {
+            for path in glob_files(vfs, &Path::new(directory).join("*.conf")) {
+                kernel_modules.process_modules_file(vfs, &path);
+            }
+        }
+        for directory in MODPROBE_DIRS {
+            for path in glob_files(vfs, &Path::new(directory).join("*.conf")) {
+                kernel_modules.process_modprobe_file(vfs, &path);
+            }
+        }
+        for directory in MODULES_DIRS {
+            kernel_modules.process_modules_dir(vfs, Path::new(directory));
+        }
+        Ok(kernel_modules)
+    }
+
+    //modules present on disk that depmod did not index, typical of rootkits installed by hand
+    pub fn unlisted_modules(&self) -> Vec<&KernelModuleFile> {
+        self.module_files
+            .iter()
+            .filter(|v| v.listed_in_dep == Some(false))
+            .collect()
+    }
+
+    pub fn install_commands(&self) -> Vec<&ModprobeDirective> {
+        self.modprobe_directives.iter().filter(|v| v.runs_command).collect()
+    }
+
+    //one module per line followed by its parameters, # and ; start comments
+    fn process_modules_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path) {
+        let modules_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        for (line_number, line) in modules_file.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            let mut columns = line.split_whitespace().map(|v| v.to_string());
+            self.load_entries.push(ModuleLoadEntry {
+                module: columns.next().unwrap_or_default(),
+                arguments: columns.collect(),
+                path: path.to_path_buf(),
+                line_number: line_number + 1,
+            });
+        }
+    }
+
+    fn process_modprobe_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path) {
+        let modprobe_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        let mut line = String::new();
+        let mut first_line_number = 0;
+        for (line_number, physical_line) in modprobe_file.lines().enumerate() {
+            if line.is_empty() {
+                first_line_number = line_number + 1;
+            }
+            if let Some(v) = physical_line.strip_suffix('\\') {
+                line.push_str(v);
+                continue;
+            }
+            line.push_str(physical_line);
+            let logical_line = std::mem::take(&mut line);
+            let logical_line = logical_line.trim();
+            if logical_line.is_empty() || logical_line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = logical_line.splitn(3, char::is_whitespace);
+            let command = match columns.next().unwrap_or_default() {
+                "install" => ModprobeCommand::Install,
+                "remove" => ModprobeCommand::Remove,
+                "blacklist" => ModprobeCommand::Blacklist,
+                "alias" => ModprobeCommand::Alias,
+                "options" => ModprobeCommand::Options,
+                "softdep" => ModprobeCommand::Softdep,
+                other => ModprobeCommand::Unknown(other.to_string()),
+            };
+            let module = columns.next().unwrap_or_default().to_string();
+            let arguments = columns.next().unwrap_or_default().trim().to_string();
+            let runs_command = matches!(command, ModprobeCommand::Install | ModprobeCommand::Remove)
+                && !DISABLING_COMMANDS.contains(&arguments.split_whitespace().next().unwrap_or_default());
+            self.modprobe_directives.push(ModprobeDirective {
+                command,
+                module,
+                arguments,
+                runs_command,
+                path: path.to_path_buf(),
+                line_number: first_line_number,
+            });
+        }
+    }
+
+    //every subdirectory is a kernel version with its own modules.dep
+    fn process_modules_dir(&mut self, vfs: &mut impl VirtualFileSystem, modules_path: &Path) {
+        let mut versions = match vfs.read_dir(modules_path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        versions.sort_by_key(|v| v.to_string());
+        for version in versions {
+            let kernel_version = match version {
+                forensic_rs::traits::vfs::VDirEntry::Directory(v) => v,
+                _ => continue,
+            };
+            //in merged /usr systems /lib/modules and /usr/lib/modules are the same directory
+            if self.module_files.iter().any(|v| v.kernel_version == kernel_version) {
+                continue;
+            }
+            let version_path = modules_path.join(&kernel_version);
+            let dependency_map = read_modules_dep(vfs, &version_path);
+
+            let mut module_paths = Vec::new();
+            walk_module_files(vfs, &version_path, &mut module_paths);
+            for path in module_paths {
+                let relative_path = path.strip_prefix(&version_path).unwrap_or(&path).to_path_buf();
+                let listed_in_dep = dependency_map
+                    .as_ref()
+                    .map(|v| v.contains(&relative_path) || v.contains(&path));
+                self.module_files.push(KernelModuleFile {
+                    kernel_version: kernel_version.clone(),
+                    path,
+                    listed_in_dep,
+                });
+            }
+        }
+    }
+}
+
+//modules.dep lines are "module path: dependencies", paths can be relative to the version directory
+fn read_modules_dep(vfs: &mut impl VirtualFileSystem, version_path: &Path) -> Option<HashSet<PathBuf>> {
+    let modules_dep = vfs.read_to_string(&version_path.join("modules.dep")).ok()?;
+    Some(
+        modules_dep
+            .lines()
+            .filter_map(|line| line.split(':').next())
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| PathBuf::from(v.trim()))
+            .collect(),
+    )
+}
+
+fn walk_module_files(vfs: &mut impl VirtualFileSystem, directory: &Path, module_paths: &mut Vec<PathBuf>) {
+    let mut entries = match vfs.read_dir(directory) {
+        Ok(v) => v,
+        Err(_e) => return,
+    };
+    entries.sort_by_key(|v| v.to_string());
+    for entry in entries {
+        match entry {
+            forensic_rs::traits::vfs::VDirEntry::Directory(name) => {
+                walk_module_files(vfs, &directory.join(name), module_paths)
+            }
+            forensic_rs::traits::vfs::VDirEntry::File(name)
+                if MODULE_EXTENSIONS.iter().any(|v| name.ends_with(v)) =>
+            {
+                module_paths.push(directory.join(name));
+            }
+            //build and source links point outside the module tree
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod kernel_modules_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_kernel_module_persistence() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let kernel_modules = KernelModules::load_kernel_modules(&mut vfs).expect("Should load kernel modules");
+
+        let loaded: Vec<&str> = kernel_modules.load_entries.iter().map(|v| v.module.as_str()).collect();
+        assert_eq!(vec!["lp", "dummy", "diamorphine"], loaded);
+        assert_eq!(vec!["numdummies=2".to_string()], kernel_modules.load_entries[1].arguments);
+
+        let install_test = ModprobeDirective {
+            command: ModprobeCommand::Install,
+            module: "dummy".to_string(),
+            arguments: "/sbin/modprobe --ignore-install dummy; /usr/local/bin/.netd &".to_string(),
+            runs_command: true,
+            path: PathBuf::from("/etc/modprobe.d/dummy.conf"),
+            line_number: 1,
+        };
+        assert_eq!(vec![&install_test], kernel_modules.install_commands());
+        assert_eq!(5, kernel_modules.modprobe_directives.len());
+        assert_eq!(ModprobeCommand::Blacklist, kernel_modules.modprobe_directives[1].command);
+
+        let unlisted: Vec<&Path> = kernel_modules.unlisted_modules().iter().map(|v| v.path.as_path()).collect();
+        assert_eq!(vec![Path::new("/lib/modules/6.2.0-39-generic/extra/diamorphine.ko")], unlisted);
+        assert_eq!(3, kernel_modules.module_files.len());
+    }
+}
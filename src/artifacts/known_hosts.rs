@@ -1,10 +1,14 @@
-pub use crate::prelude::{UserInfo, known_hosts};
+pub use crate::prelude::{UserInfo, known_hosts, read_rotated_lines, wildcard_match};
 pub use crate::ChRootFileSystem;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::NaiveDateTime;
 pub use forensic_rs::{
     core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
 };
+use hmac::{Hmac, Mac};
 use lazy_static::lazy_static;
 use regex::Regex;
+use sha1::Sha1;
 pub use std::{
     fs,
     io::BufRead,
@@ -17,38 +21,408 @@ pub struct KnownHost {
     pub key_type: String,
     pub public_key: String,
     pub comment: String,
+    pub matched_hosts: Vec<HostCandidate>,
+    pub host_patterns: Vec<HostPattern>,
+    pub marker: KnownHostMarker,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum KnownHostMarker {
+    #[default]
+    None,
+    CertAuthority,
+    Revoked,
+}
+
+//one of the comma separated entries of the host field: host, [host]:port, *.domain, !host or |1|salt|hash
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HostPattern {
+    pub pattern: String,
+    pub port: Option<u16>,
+    pub negated: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum HostCandidateSource {
+    EtcHosts,
+    ShellHistory,
+    AuthLog,
+    #[default]
+    UserSupplied,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HostCandidate {
+    pub host: String,
+    pub port: Option<u16>,
+    pub source: HostCandidateSource,
 }
 
 lazy_static! {
     pub static ref KNOWN_HOSTS_COMPONENTS: Regex =
-        Regex::new(r#"^([^\s]+)\s+([^\s]+)\s+([^\s]+)\s*(.*)$"#).unwrap();
+        Regex::new(r#"^(?:@([\w-]+)\s+)?(\S+)\s+(\S+)\s+(\S+)\s*(.*)$"#).unwrap();
+    pub static ref AUTH_LOG_REMOTE_HOST: Regex =
+        Regex::new(r#"(?:\sfrom\s+|\srhost=)([0-9A-Za-z][0-9A-Za-z.:_-]*)"#).unwrap();
 }
 
+//ssh and scp options that consume the next argument
+const SSH_OPTIONS_WITH_ARGUMENT: &str = "BbcDEeFIiJLlmOoPpQRSWw";
+
+//paths where sshd logs the remote hosts of each connection
+const AUTH_LOG_PATHS: [&str; 2] = ["/var/log/auth.log", "/var/log/secure"];
+
 impl KnownHost {
+    //reads the known_hosts files of the user, missing files are skipped
     pub fn get_known_hosts(
         vfs: &mut impl VirtualFileSystem,
         user_home_path: PathBuf,
     ) -> ForensicResult<Vec<Self>> {
-        let known_hosts = vfs.read_to_string(
-            user_home_path.join(".ssh/known_hosts").as_path())?;
-    
-        let reader_groups = std::io::BufReader::new(known_hosts.as_bytes());
+        Self::process_known_hosts_files(vfs, &[
+            user_home_path.join(".ssh/known_hosts"),
+            user_home_path.join(".ssh/known_hosts2"),
+        ])
+    }
+
+    //reads the known_hosts files shared by every user of the system
+    pub fn get_system_known_hosts(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Vec<Self>> {
+        Self::process_known_hosts_files(vfs, &[
+            PathBuf::from("/etc/ssh/ssh_known_hosts"),
+            PathBuf::from("/etc/ssh/ssh_known_hosts2"),
+        ])
+    }
+
+    pub fn process_known_hosts_files(
+        vfs: &mut impl VirtualFileSystem,
+        paths: &[PathBuf],
+    ) -> ForensicResult<Vec<Self>> {
         let mut system_known_hosts: Vec<Self> = Vec::new();
-    
-        for known_host in reader_groups.lines() {
-            let known_host = known_host?;
-            let captures = KNOWN_HOSTS_COMPONENTS.captures(&known_host).unwrap();
-            let known_host = Self {
-                hostname: captures.get(1).unwrap().as_str().trim().to_string(),
-                key_type: captures.get(2).unwrap().as_str().trim().to_string(),
-                public_key: captures.get(3).unwrap().as_str().trim().to_string(),
-                comment: captures.get(4).unwrap().as_str().trim().to_string(),
+
+        for path in paths {
+            let known_hosts = match vfs.read_to_string(path.as_path()) {
+                Ok(v) => v,
+                Err(_e) => continue,
             };
-            system_known_hosts.push(known_host);
+            for known_host in known_hosts.lines() {
+                //blank, commented and malformed lines are ignored by ssh too
+                if let Some(mut known_host) = Self::parse_known_host_line(known_host) {
+                    known_host.path = path.clone();
+                    system_known_hosts.push(known_host);
+                }
+            }
         }
-    
+
         Ok(system_known_hosts)
     }
+
+    pub fn parse_known_host_line(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let captures = KNOWN_HOSTS_COMPONENTS.captures(line)?;
+        let marker = match captures.get(1).map(|v| v.as_str()) {
+            None => KnownHostMarker::None,
+            Some("cert-authority") => KnownHostMarker::CertAuthority,
+            Some("revoked") => KnownHostMarker::Revoked,
+            Some(_) => return None,
+        };
+        let hostname = captures.get(2)?.as_str().trim().to_string();
+        Some(Self {
+            host_patterns: hostname.split(',').filter_map(HostPattern::parse).collect(),
+            hostname,
+            key_type: captures.get(3)?.as_str().trim().to_string(),
+            public_key: captures.get(4)?.as_str().trim().to_string(),
+            comment: captures.get(5)?.as_str().trim().to_string(),
+            matched_hosts: Vec::new(),
+            marker,
+            path: PathBuf::new(),
+        })
+    }
+
+    pub fn is_hashed(&self) -> bool {
+        self.host_patterns.iter().any(|v| v.is_hashed())
+    }
+
+    pub fn is_revoked(&self) -> bool {
+        self.marker == KnownHostMarker::Revoked
+    }
+
+    //checks if the candidate produces the same HMAC-SHA1 as one of the hashed patterns
+    pub fn matches_candidate(&self, candidate: &HostCandidate) -> bool {
+        self.host_patterns
+            .iter()
+            .any(|v| v.is_hashed() && v.matches(&candidate.host, candidate.port))
+    }
+
+    //same rules as ssh: a matching negated pattern rejects the host even if others accept it
+    pub fn matches_host(&self, host: &str, port: Option<u16>) -> bool {
+        let mut matched = false;
+        for pattern in &self.host_patterns {
+            if pattern.matches(host, port) {
+                if pattern.negated {
+                    return false;
+                }
+                matched = true;
+            }
+        }
+        matched
+    }
+
+    //annotates every hashed known host with the candidates that match its hash
+    pub fn resolve_hashed_hosts(known_hosts: &mut [KnownHost], candidates: &[HostCandidate]) {
+        for known_host in known_hosts.iter_mut().filter(|v| v.is_hashed()) {
+            for candidate in candidates {
+                if known_host.matches_candidate(candidate)
+                    && !known_host.matched_hosts.contains(candidate)
+                {
+                    known_host.matched_hosts.push(candidate.clone());
+                }
+            }
+        }
+    }
+}
+
+impl HostPattern {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (negated, value) = match value.strip_prefix('!') {
+            Some(v) => (true, v),
+            None => (false, value),
+        };
+        if value.is_empty() {
+            return None;
+        }
+        if let Some(bracketed) = value.strip_prefix('[') {
+            if let Some((host, rest)) = bracketed.split_once(']') {
+                return Some(Self {
+                    pattern: host.to_string(),
+                    port: rest.strip_prefix(':').and_then(|v| v.parse::<u16>().ok()),
+                    negated,
+                });
+            }
+        }
+        Some(Self {
+            pattern: value.to_string(),
+            port: None,
+            negated,
+        })
+    }
+
+    //hashed entries have the form |1|base64(salt)|base64(hmac_sha1(salt, host))
+    pub fn is_hashed(&self) -> bool {
+        self.pattern.starts_with("|1|")
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.pattern.contains(['*', '?'])
+    }
+
+    pub fn matches(&self, host: &str, port: Option<u16>) -> bool {
+        if self.is_hashed() {
+            return self.matches_hash(host, port);
+        }
+        //without brackets the entry only applies to the default port
+        let port = port.filter(|v| *v != 22);
+        self.port.filter(|v| *v != 22) == port
+            && wildcard_match(&self.pattern.to_lowercase(), &host.to_lowercase())
+    }
+
+    fn matches_hash(&self, host: &str, port: Option<u16>) -> bool {
+        let mut parts = self.pattern["|1|".len()..].split('|');
+        let (salt, hash) = match (parts.next(), parts.next()) {
+            (Some(salt), Some(hash)) => (salt, hash),
+            _ => return false,
+        };
+        let (salt, hash) = match (STANDARD.decode(salt), STANDARD.decode(hash)) {
+            (Ok(salt), Ok(hash)) => (salt, hash),
+            _ => return false,
+        };
+        let mut mac = match Hmac::<Sha1>::new_from_slice(&salt) {
+            Ok(v) => v,
+            Err(_) => return false,
+        };
+        let candidate = HostCandidate::new(host, port, HostCandidateSource::UserSupplied);
+        mac.update(candidate.known_hosts_name().as_bytes());
+        mac.verify_slice(&hash).is_ok()
+    }
+}
+
+impl HostCandidate {
+    pub fn new(host: &str, port: Option<u16>, source: HostCandidateSource) -> Self {
+        Self {
+            host: host.trim_start_matches('[').trim_end_matches(']').to_string(),
+            port,
+            source,
+        }
+    }
+
+    //name as ssh writes it in known_hosts before hashing: host or [host]:port
+    pub fn known_hosts_name(&self) -> String {
+        match self.port {
+            Some(port) if port != 22 => format!("[{}]:{}", self.host, port),
+            _ => self.host.clone(),
+        }
+    }
+
+    //builds candidates from a list of host, host:port or [host]:port entries
+    pub fn from_list(hosts: &[String]) -> Vec<Self> {
+        hosts
+            .iter()
+            .filter_map(|v| Self::parse_host_port(v.trim(), HostCandidateSource::UserSupplied))
+            .collect()
+    }
+
+    //returns every address and hostname of /etc/hosts
+    pub fn from_etc_hosts(vfs: &mut impl VirtualFileSystem) -> Vec<Self> {
+        let hosts = match vfs.read_to_string(Path::new("/etc/hosts")) {
+            Ok(v) => v,
+            Err(_e) => return Vec::new(),
+        };
+        let mut candidates = Vec::new();
+        for line in hosts.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for host in line.split_whitespace() {
+                push_unique(
+                    &mut candidates,
+                    Self::new(host, None, HostCandidateSource::EtcHosts),
+                );
+            }
+        }
+        candidates
+    }
+
+    //returns the remote hosts of the ssh and scp commands of a shell history
+    pub fn from_shell_history(commands: &[(Option<NaiveDateTime>, String)]) -> Vec<Self> {
+        let mut candidates = Vec::new();
+        for (_, command) in commands {
+            for candidate in Self::parse_ssh_command(command) {
+                push_unique(&mut candidates, candidate);
+            }
+        }
+        candidates
+    }
+
+    //returns the remote hosts that appear in the sshd authentication logs and their rotations
+    pub fn from_auth_logs(vfs: &mut impl VirtualFileSystem) -> Vec<Self> {
+        let mut candidates = Vec::new();
+        for path in AUTH_LOG_PATHS {
+            for rotated_line in read_rotated_lines(vfs, Path::new(path)) {
+                for captures in AUTH_LOG_REMOTE_HOST.captures_iter(&rotated_line.line) {
+                    let host = captures.get(1).unwrap().as_str();
+                    push_unique(
+                        &mut candidates,
+                        Self::new(host, None, HostCandidateSource::AuthLog),
+                    );
+                }
+            }
+        }
+        candidates
+    }
+
+    //extracts the destination of "ssh [options] [user@]host" and "scp ... [user@]host:path"
+    pub fn parse_ssh_command(command: &str) -> Vec<Self> {
+        let mut candidates = Vec::new();
+        let mut words = command.split_whitespace();
+        while let Some(word) = words.next() {
+            let program = word.rsplit('/').next().unwrap_or_default();
+            if program != "ssh" && program != "scp" {
+                continue;
+            }
+            let mut port: Option<u16> = None;
+            let mut destinations: Vec<&str> = Vec::new();
+            while let Some(arg) = words.next() {
+                if matches!(arg, ";" | "&&" | "||" | "|") {
+                    break;
+                }
+                if let Some(option) = arg.strip_prefix('-') {
+                    //flags can be grouped (-vp 22) and the value attached to the flag (-p2222)
+                    let position = match option.find(|c| SSH_OPTIONS_WITH_ARGUMENT.contains(c)) {
+                        Some(v) => v,
+                        None => continue,
+                    };
+                    let flag = option[position..].chars().next().unwrap_or_default();
+                    let value = match &option[position + 1..] {
+                        "" => words.next().unwrap_or_default(),
+                        attached => attached,
+                    };
+                    if (program == "ssh" && flag == 'p') || (program == "scp" && flag == 'P') {
+                        port = value.parse::<u16>().ok();
+                    }
+                    continue;
+                }
+                destinations.push(arg);
+            }
+            if program == "ssh" {
+                if let Some(destination) = destinations.first() {
+                    if let Some(mut candidate) = Self::parse_ssh_destination(destination) {
+                        candidate.port = candidate.port.or(port);
+                        candidates.push(candidate);
+                    }
+                }
+            } else {
+                for destination in destinations {
+                    if let Some(candidate) = Self::parse_scp_destination(destination, port) {
+                        candidates.push(candidate);
+                    }
+                }
+            }
+        }
+        candidates
+    }
+
+    fn parse_ssh_destination(destination: &str) -> Option<Self> {
+        let destination = destination.strip_prefix("ssh://").unwrap_or(destination);
+        let host = destination.rsplit('@').next().unwrap_or_default();
+        Self::parse_host_port(host, HostCandidateSource::ShellHistory)
+    }
+
+    fn parse_scp_destination(destination: &str, port: Option<u16>) -> Option<Self> {
+        if let Some(destination) = destination.strip_prefix("scp://") {
+            let host = destination.split('/').next().unwrap_or_default();
+            let host = host.rsplit('@').next().unwrap_or_default();
+            let mut candidate = Self::parse_host_port(host, HostCandidateSource::ShellHistory)?;
+            candidate.port = candidate.port.or(port);
+            return Some(candidate);
+        }
+        //local paths do not have a colon before the first slash
+        let host = destination.rsplit('@').next().unwrap_or_default();
+        let host = if let Some(bracketed) = host.strip_prefix('[') {
+            bracketed.split(']').next().unwrap_or_default()
+        } else {
+            match host.split_once(':') {
+                Some((host, _)) if !host.contains('/') => host,
+                _ => return None,
+            }
+        };
+        if host.is_empty() {
+            return None;
+        }
+        Some(Self::new(host, port, HostCandidateSource::ShellHistory))
+    }
+
+    fn parse_host_port(value: &str, source: HostCandidateSource) -> Option<Self> {
+        if value.is_empty() {
+            return None;
+        }
+        if let Some(bracketed) = value.strip_prefix('[') {
+            let (host, rest) = bracketed.split_once(']')?;
+            let port = rest.strip_prefix(':').and_then(|v| v.parse::<u16>().ok());
+            return Some(Self::new(host, port, source));
+        }
+        //a bare IPv6 address has more than one colon and no port
+        match value.split_once(':') {
+            Some((host, port)) if !port.contains(':') => {
+                Some(Self::new(host, port.parse::<u16>().ok(), source))
+            }
+            _ => Some(Self::new(value, None, source)),
+        }
+    }
+}
+
+fn push_unique(candidates: &mut Vec<HostCandidate>, candidate: HostCandidate) {
+    if !candidates.contains(&candidate) {
+        candidates.push(candidate);
+    }
 }
 
 #[test]
@@ -61,6 +435,9 @@ fn should_process_known_hosts_file() {
     let user_info = UserInfo {
         name: "forensicrs".to_string(),
         id: 1,
+        gid: 1000,
+        gecos: String::new(),
+        password: Default::default(),
         home: PathBuf::from("/home/forensicrs"),
         shell: "/bin/bash".to_string(),
         groups: Vec::new(),
@@ -73,7 +450,15 @@ fn should_process_known_hosts_file() {
                 hostname: "|1|TfKl866biYXUtnTkYkd0hRxU3qU=|8wzgJpCzm2GrhwDS507gudKHlO4=".to_string(),
                 key_type: "ssh-ed25519".to_string(),
                 public_key: String::from(r#"AAAAC3NzaC1lZDI1NTE5AAAAIDwrpaWIiSPnPg5HPKCVfYXzkTF6m3eTMSfnopZSiNE8"#),
-                comment: "".to_string()
+                comment: "".to_string(),
+                matched_hosts: Vec::new(),
+                host_patterns: vec![HostPattern {
+                    pattern: "|1|TfKl866biYXUtnTkYkd0hRxU3qU=|8wzgJpCzm2GrhwDS507gudKHlO4=".to_string(),
+                    port: None,
+                    negated: false,
+                }],
+                marker: KnownHostMarker::None,
+                path: PathBuf::from("/home/forensicrs/.ssh/known_hosts"),
             };
             assert_eq!(authorized_key_test, keys[0]);
         },
@@ -82,3 +467,78 @@ fn should_process_known_hosts_file() {
         }
     }
 }
+
+#[test]
+fn should_resolve_hashed_known_hosts() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    let mut known_hosts = KnownHost::get_known_hosts(&mut vfs, PathBuf::from("/home/forensicrs"))
+        .expect("Should process known_hosts");
+
+    let history = vec![(None, "ssh -p 2222 admin@git.internal.lan".to_string())];
+    let mut candidates = HostCandidate::from_etc_hosts(&mut vfs);
+    candidates.extend(HostCandidate::from_shell_history(&history));
+    candidates.extend(HostCandidate::from_auth_logs(&mut vfs));
+    candidates.extend(HostCandidate::from_list(&["10.0.0.1".to_string()]));
+    KnownHost::resolve_hashed_hosts(&mut known_hosts, &candidates);
+
+    let resolved: Vec<&KnownHost> = known_hosts
+        .iter()
+        .filter(|v| !v.matched_hosts.is_empty())
+        .collect();
+    assert_eq!(2, resolved.len());
+    assert_eq!(
+        vec![HostCandidate::new("192.168.1.10", None, HostCandidateSource::EtcHosts)],
+        resolved[0].matched_hosts
+    );
+    assert_eq!(
+        vec![HostCandidate::new("git.internal.lan", Some(2222), HostCandidateSource::ShellHistory)],
+        resolved[1].matched_hosts
+    );
+}
+
+#[test]
+fn should_parse_ssh_and_scp_commands() {
+    assert_eq!(
+        vec![HostCandidate::new("10.1.1.1", Some(2200), HostCandidateSource::ShellHistory)],
+        HostCandidate::parse_ssh_command("ssh -i ~/.ssh/id_rsa -p2200 -o StrictHostKeyChecking=no root@10.1.1.1 id")
+    );
+    assert_eq!(
+        vec![
+            HostCandidate::new("fileserver", Some(2200), HostCandidateSource::ShellHistory),
+            HostCandidate::new("fe80::1", Some(2200), HostCandidateSource::ShellHistory),
+        ],
+        HostCandidate::parse_ssh_command("scp -P 2200 ./a.txt user@fileserver:/tmp/ [fe80::1]:/tmp/")
+    );
+}
+
+
+#[test]
+fn should_process_system_known_hosts_markers_and_patterns() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    let known_hosts = KnownHost::get_system_known_hosts(&mut vfs)
+        .expect("Should process ssh_known_hosts");
+
+    assert_eq!(4, known_hosts.len());
+    assert_eq!(KnownHostMarker::CertAuthority, known_hosts[0].marker);
+    assert!(known_hosts[0].matches_host("build01.corp.example", None));
+    assert!(!known_hosts[0].matches_host("bastion.corp.example", None));
+    assert!(known_hosts[1].is_revoked());
+    assert_eq!(
+        vec![
+            HostPattern { pattern: "gitlab.corp.example".to_string(), port: None, negated: false },
+            HostPattern { pattern: "10.0.0.15".to_string(), port: Some(2222), negated: false },
+        ],
+        known_hosts[2].host_patterns
+    );
+    assert!(known_hosts[2].matches_host("10.0.0.15", Some(2222)));
+    assert!(!known_hosts[2].matches_host("10.0.0.15", None));
+    assert_eq!("deploy key", known_hosts[3].comment);
+}
@@ -0,0 +1,363 @@
+pub use crate::prelude::{glob_files, SystemInfo};
+pub use crate::{BashRcConfig, ChRootFileSystem};
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    collections::HashMap,
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+const OLD_CACHE_MAGIC: &[u8] = b"ld.so-1.7.0";
+const NEW_CACHE_MAGIC: &[u8] = b"glibc-ld.so.cache1.1";
+//ldconfig stops following include directives after this depth
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+//directories searched by the dynamic linker without any configuration
+const STANDARD_LIBRARY_DIRS: [&str; 10] = [
+    "/lib", "/lib32", "/lib64", "/libx32", "/usr/lib", "/usr/lib32", "/usr/lib64", "/usr/libx32",
+    "/usr/local/lib", "/usr/local/lib64",
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PreloadSource {
+    //listed in /etc/ld.so.preload
+    #[default]
+    LdSoPreload,
+    //LD_PRELOAD exported in the bash configuration of the user
+    BashExport(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PreloadedLibrary {
+    pub library: String,
+    pub source: PreloadSource,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LibrarySearchDir {
+    pub directory: PathBuf,
+    pub standard: bool,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum LdCacheFormat {
+    #[default]
+    Missing,
+    Old,
+    New,
+    //old format followed by the new one, written by ldconfig for compatibility
+    Compat,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LdCacheEntry {
+    pub library: String,
+    pub path: PathBuf,
+    pub flags: i32,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LdCache {
+    pub format: LdCacheFormat,
+    pub entries: Vec<LdCacheEntry>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LinkerConfig {
+    pub preloads: Vec<PreloadedLibrary>,
+    pub search_dirs: Vec<LibrarySearchDir>,
+    pub config_files: Vec<PathBuf>,
+    pub cache: LdCache,
+}
+
+impl LinkerConfig {
+    pub fn load_linker_config(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut linker_config = Self::default();
+        linker_config.process_preload_file(vfs);
+        linker_config.process_config_file(vfs, Path::new("/etc/ld.so.conf"), 0);
+        linker_config.cache = match vfs.read_all(Path::new("/etc/ld.so.cache")) {
+            Ok(v) => LdCache::parse(&v),
+            Err(_e) => LdCache::default(),
+        };
+        Ok(linker_config)
+    }
+
+    //linker configuration plus the LD_PRELOAD exports of every user of the system
+    pub fn load_system_linker_config(
+        vfs: &mut impl VirtualFileSystem,
+        system_info: &SystemInfo,
+    ) -> ForensicResult<Self> {
+        let mut linker_config = Self::load_linker_config(vfs)?;
+        for user in &system_info.users {
+            let bash_config = BashRcConfig::load_bash_config(user.clone(), vfs)?;
+            linker_config.add_bash_exports(&user.name, &bash_config);
+        }
+        Ok(linker_config)
+    }
+
+    //LD_PRELOAD accepts libraries separated by spaces or colons
+    pub fn add_bash_exports(&mut self, username: &str, bash_config: &BashRcConfig) {
+        let values = match bash_config.exports.get("LD_PRELOAD") {
+            Some(v) => v,
+            None => return,
+        };
+        for value in values {
+            for library in value.split([' ', ':']).filter(|v| !v.is_empty()) {
+                self.preloads.push(PreloadedLibrary {
+                    library: library.to_string(),
+                    source: PreloadSource::BashExport(username.to_string()),
+                    path: PathBuf::new(),
+                    line_number: 0,
+                });
+            }
+        }
+    }
+
+    pub fn nonstandard_search_dirs(&self) -> Vec<&LibrarySearchDir> {
+        self.search_dirs.iter().filter(|v| !v.standard).collect()
+    }
+
+    //libraries of the cache indexed by name, a name can be provided by several paths
+    pub fn cached_libraries(&self) -> HashMap<String, Vec<PathBuf>> {
+        let mut libraries: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for entry in &self.cache.entries {
+            libraries.entry(entry.library.clone()).or_default().push(entry.path.clone());
+        }
+        libraries
+    }
+
+    fn process_preload_file(&mut self, vfs: &mut impl VirtualFileSystem) {
+        let preload_path = Path::new("/etc/ld.so.preload");
+        let preload_file = match vfs.read_to_string(preload_path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        for (line_number, line) in preload_file.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            for library in line.split([' ', '\t', ':']).filter(|v| !v.is_empty()) {
+                self.preloads.push(PreloadedLibrary {
+                    library: library.to_string(),
+                    source: PreloadSource::LdSoPreload,
+                    path: preload_path.to_path_buf(),
+                    line_number: line_number + 1,
+                });
+            }
+        }
+    }
+
+    //directories one per line, relative include patterns are resolved against the directory of the file
+    fn process_config_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path, depth: usize) {
+        if depth > MAX_INCLUDE_DEPTH || self.config_files.contains(&path.to_path_buf()) {
+            return;
+        }
+        let config_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        self.config_files.push(path.to_path_buf());
+
+        for (line_number, line) in config_file.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with("hwcap ") {
+                continue;
+            }
+            if let Some(pattern) = line.strip_prefix("include ") {
+                for pattern in pattern.split_whitespace() {
+                    let pattern = path.parent().unwrap_or(Path::new("/")).join(pattern);
+                    for included_path in glob_files(vfs, &pattern) {
+                        self.process_config_file(vfs, &included_path, depth + 1);
+                    }
+                }
+                continue;
+            }
+            //old ldconfig accepted dir=libtype and several directories per line
+            for directory in line.split([' ', '\t', ':', ',']).filter(|v| !v.is_empty()) {
+                let directory = PathBuf::from(directory.split('=').next().unwrap_or_default());
+                self.search_dirs.push(LibrarySearchDir {
+                    standard: is_standard_library_dir(&directory),
+                    directory,
+                    path: path.to_path_buf(),
+                    line_number: line_number + 1,
+                });
+            }
+        }
+    }
+}
+
+impl LdCache {
+    //the new format can be found alone or after the entries of the old one
+    pub fn parse(contents: &[u8]) -> Self {
+        if contents.starts_with(NEW_CACHE_MAGIC) {
+            return Self {
+                format: LdCacheFormat::New,
+                entries: parse_new_cache(contents).unwrap_or_default(),
+            };
+        }
+        if !contents.starts_with(OLD_CACHE_MAGIC) {
+            return Self::default();
+        }
+        let nlibs = read_u32(contents, 12, false).unwrap_or_default() as usize;
+        let new_cache_start = nlibs.checked_mul(12).and_then(|v| v.checked_add(16 + 7)).map(|v| v & !7);
+        if let Some(new_cache) = new_cache_start.and_then(|v| contents.get(v..)) {
+            if new_cache.starts_with(NEW_CACHE_MAGIC) {
+                return Self {
+                    format: LdCacheFormat::Compat,
+                    entries: parse_new_cache(new_cache).unwrap_or_default(),
+                };
+            }
+        }
+        Self {
+            format: LdCacheFormat::Old,
+            entries: parse_old_cache(contents, nlibs).unwrap_or_default(),
+        }
+    }
+}
+
+//entries of 12 bytes (flags, key, value), the offsets are relative to the string table after them
+fn parse_old_cache(contents: &[u8], nlibs: usize) -> Option<Vec<LdCacheEntry>> {
+    //the count comes from the file, it cannot claim more entries than the file holds
+    let strings = contents.get(nlibs.checked_mul(12)?.checked_add(16)?..)?;
+    let mut entries = Vec::with_capacity(nlibs);
+    for i in 0..nlibs {
+        let offset = 16 + i * 12;
+        entries.push(LdCacheEntry {
+            flags: read_u32(contents, offset, false)? as i32,
+            library: read_string(strings, read_u32(contents, offset + 4, false)? as usize)?,
+            path: PathBuf::from(read_string(strings, read_u32(contents, offset + 8, false)? as usize)?),
+        });
+    }
+    Some(entries)
+}
+
+//header of 48 bytes and entries of 24 bytes, the offsets are relative to the header
+fn parse_new_cache(contents: &[u8]) -> Option<Vec<LdCacheEntry>> {
+    //byte 28 holds the endianness, 3 means big endian
+    let big_endian = *contents.get(28)? == 3;
+    let nlibs = read_u32(contents, 20, big_endian)? as usize;
+    if nlibs.checked_mul(24)?.checked_add(48)? > contents.len() {
+        return None;
+    }
+    let mut entries = Vec::with_capacity(nlibs);
+    for i in 0..nlibs {
+        let offset = 48 + i * 24;
+        entries.push(LdCacheEntry {
+            flags: read_u32(contents, offset, big_endian)? as i32,
+            library: read_string(contents, read_u32(contents, offset + 4, big_endian)? as usize)?,
+            path: PathBuf::from(read_string(contents, read_u32(contents, offset + 8, big_endian)? as usize)?),
+        });
+    }
+    Some(entries)
+}
+
+fn read_u32(contents: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = contents.get(offset..offset + 4)?.try_into().ok()?;
+    Some(match big_endian {
+        true => u32::from_be_bytes(bytes),
+        false => u32::from_le_bytes(bytes),
+    })
+}
+
+fn read_string(contents: &[u8], offset: usize) -> Option<String> {
+    let bytes = contents.get(offset..)?;
+    let end = bytes.iter().position(|v| *v == 0)?;
+    Some(String::from_utf8_lossy(&bytes[..end]).to_string())
+}
+
+//standard directories and their multiarch subdirectories like /usr/lib/x86_64-linux-gnu
+fn is_standard_library_dir(directory: &Path) -> bool {
+    let directory = directory.to_string_lossy();
+    let directory = directory.trim_end_matches('/');
+    if STANDARD_LIBRARY_DIRS.contains(&directory) {
+        return true;
+    }
+    match directory.rsplit_once('/') {
+        Some((parent, name)) => {
+            ["/lib", "/usr/lib", "/usr/local/lib"].contains(&parent) && name.contains("-linux-")
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod linker_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_linker_config() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let mut linker_config = LinkerConfig::load_linker_config(&mut vfs).expect("Should load linker config");
+
+        let preload_test = PreloadedLibrary {
+            library: "/usr/lib/x86_64-linux-gnu/libprocesshider.so".to_string(),
+            source: PreloadSource::LdSoPreload,
+            path: PathBuf::from("/etc/ld.so.preload"),
+            line_number: 2,
+        };
+        assert_eq!(vec![preload_test], linker_config.preloads);
+
+        let mut bash_config = BashRcConfig::default();
+        bash_config.exports.insert(
+            "LD_PRELOAD".to_string(),
+            ["/tmp/.x/libc.so.6 /dev/shm/hook.so".to_string()].into_iter().collect(),
+        );
+        linker_config.add_bash_exports("forensicrs", &bash_config);
+        assert_eq!(3, linker_config.preloads.len());
+        assert_eq!(PreloadSource::BashExport("forensicrs".to_string()), linker_config.preloads[2].source);
+
+        let nonstandard: Vec<&Path> = linker_config
+            .nonstandard_search_dirs()
+            .iter()
+            .map(|v| v.directory.as_path())
+            .collect();
+        assert_eq!(vec![Path::new("/opt/.cache/lib")], nonstandard);
+        assert_eq!(Path::new("/etc/ld.so.conf.d/zz-local.conf"), linker_config.nonstandard_search_dirs()[0].path);
+        assert_eq!(4, linker_config.config_files.len());
+
+        assert_eq!(LdCacheFormat::New, linker_config.cache.format);
+        let libraries = linker_config.cached_libraries();
+        assert_eq!(
+            vec![PathBuf::from("/lib/x86_64-linux-gnu/libc.so.6")],
+            libraries["libc.so.6"]
+        );
+        assert_eq!(
+            vec![PathBuf::from("/opt/.cache/lib/libpam.so.0")],
+            libraries["libpam.so.0"]
+        );
+    }
+
+    #[test]
+    fn should_parse_old_ld_cache() {
+        let mut contents = b"ld.so-1.7.0\0".to_vec();
+        contents.extend(1u32.to_le_bytes());
+        contents.extend(0x0303i32.to_le_bytes());
+        contents.extend(0u32.to_le_bytes());
+        contents.extend(10u32.to_le_bytes());
+        contents.extend(b"libz.so.1\0/lib/libz.so.1\0");
+
+        let cache = LdCache::parse(&contents);
+        assert_eq!(LdCacheFormat::Old, cache.format);
+        let entry_test = LdCacheEntry {
+            library: "libz.so.1".to_string(),
+            path: PathBuf::from("/lib/libz.so.1"),
+            flags: 0x0303,
+        };
+        assert_eq!(vec![entry_test], cache.entries);
+
+        //a count larger than the file is rejected instead of allocated
+        let mut hostile = NEW_CACHE_MAGIC.to_vec();
+        hostile.resize(48, 0);
+        hostile[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(LdCache::parse(&hostile).entries.is_empty());
+    }
+}
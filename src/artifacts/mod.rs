@@ -5,3 +5,18 @@ pub mod crontab;
 pub mod known_hosts;
 pub mod authorized_keys;
 pub mod services;
+pub mod ssh_config;
+pub mod ssh_inventory;
+pub mod sudoers;
+pub mod accounts;
+pub mod linker;
+pub mod startup_hooks;
+pub mod pam;
+pub mod kernel_modules;
+pub mod udev;
+pub mod desktop;
+pub mod packages;
+pub mod integrity;
+pub mod package_history;
+pub mod containers;
+pub mod credentials;
@@ -0,0 +1,457 @@
+pub use crate::prelude::{read_decompressed, rotated_family, with_sqlite_database, FileMetadata, SystemInfo, UserInfo};
+pub use crate::ChRootFileSystem;
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+const DPKG_LOGS: &str = "/var/log/dpkg.log";
+const APT_HISTORY_LOGS: &str = "/var/log/apt/history.log";
+const APT_TERM_LOGS: &str = "/var/log/apt/term.log";
+const YUM_LOGS: &str = "/var/log/yum.log";
+const DNF_HISTORY_PATH: &str = "/var/lib/dnf/history.sqlite";
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PackageAction {
+    #[default]
+    Install,
+    Upgrade,
+    Downgrade,
+    Reinstall,
+    Remove,
+    Purge,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum HistorySource {
+    #[default]
+    Dpkg,
+    Apt,
+    Yum,
+    Dnf,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PackageEvent {
+    pub timestamp: Option<NaiveDateTime>,
+    pub action: PackageAction,
+    pub package: String,
+    pub architecture: Option<String>,
+    pub old_version: Option<String>,
+    pub version: Option<String>,
+    pub source: HistorySource,
+    pub path: PathBuf,
+    pub commandline: Option<String>,
+    //apt Requested-By value or dnf user id, only recorded when the tool knows who asked for it
+    pub requested_by: Option<String>,
+    pub user: Option<UserInfo>,
+}
+
+//one "Log started" block of the apt term.log with the output of dpkg
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AptTerminalLog {
+    pub start: Option<NaiveDateTime>,
+    pub end: Option<NaiveDateTime>,
+    pub path: PathBuf,
+    pub contents: String,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct PackageHistory {
+    //sorted by timestamp, events without one go first
+    pub events: Vec<PackageEvent>,
+    pub terminal_logs: Vec<AptTerminalLog>,
+}
+
+impl PackageHistory {
+    pub fn load_package_history(
+        system_info: &SystemInfo,
+        vfs: &mut impl VirtualFileSystem,
+    ) -> ForensicResult<Self> {
+        let mut package_history = Self::default();
+        for path in rotated_family(vfs, Path::new(DPKG_LOGS)) {
+            if let Some(contents) = read_log(vfs, &path) {
+                package_history.process_dpkg_log(&contents, &path);
+            }
+        }
+        for path in rotated_family(vfs, Path::new(APT_HISTORY_LOGS)) {
+            if let Some(contents) = read_log(vfs, &path) {
+                package_history.process_apt_history(&contents, &path, system_info);
+            }
+        }
+        for path in rotated_family(vfs, Path::new(APT_TERM_LOGS)) {
+            if let Some(contents) = read_log(vfs, &path) {
+                package_history.process_apt_term_log(&contents, &path);
+            }
+        }
+        for path in rotated_family(vfs, Path::new(YUM_LOGS)) {
+            if let Some(contents) = read_log(vfs, &path) {
+                let modified = FileMetadata::from_vfs(vfs, &path).and_then(|v| v.modified);
+                package_history.process_yum_log(&contents, &path, modified);
+            }
+        }
+        package_history.process_dnf_history(vfs, system_info);
+
+        package_history.events.sort_by_key(|v| v.timestamp);
+        package_history.terminal_logs.sort_by_key(|v| v.start);
+        Ok(package_history)
+    }
+
+    pub fn events_for_package(&self, package: &str) -> Vec<&PackageEvent> {
+        self.events.iter().filter(|v| v.package == package).collect()
+    }
+
+    //"date time action package:arch old_version new_version", <none> stands for no version
+    fn process_dpkg_log(&mut self, contents: &str, path: &Path) {
+        for line in contents.lines() {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 6 {
+                continue;
+            }
+            let action = match columns[2] {
+                "install" => PackageAction::Install,
+                "upgrade" => PackageAction::Upgrade,
+                "remove" => PackageAction::Remove,
+                "purge" => PackageAction::Purge,
+                _ => continue,
+            };
+            let (package, architecture) = split_architecture(columns[3]);
+            self.events.push(PackageEvent {
+                timestamp: parse_date_time(&format!("{} {}", columns[0], columns[1])),
+                action,
+                package,
+                architecture,
+                old_version: dpkg_version(columns[4]),
+                version: dpkg_version(columns[5]),
+                source: HistorySource::Dpkg,
+                path: path.to_path_buf(),
+                ..Default::default()
+            });
+        }
+    }
+
+    //blocks from Start-Date to End-Date, every action lists "package:arch (versions), ..."
+    fn process_apt_history(&mut self, contents: &str, path: &Path, system_info: &SystemInfo) {
+        for block in contents.split("\n\n") {
+            let mut timestamp = None;
+            let mut commandline = None;
+            let mut requested_by = None;
+            let mut actions = Vec::new();
+            for line in block.lines() {
+                let (field, value) = match line.split_once(": ") {
+                    Some((field, value)) => (field, value.trim()),
+                    None => continue,
+                };
+                match field {
+                    "Start-Date" => timestamp = parse_date_time(value),
+                    "Commandline" => commandline = Some(value.to_string()),
+                    "Requested-By" => requested_by = Some(value.to_string()),
+                    "Install" => actions.push((PackageAction::Install, value)),
+                    "Upgrade" => actions.push((PackageAction::Upgrade, value)),
+                    "Downgrade" => actions.push((PackageAction::Downgrade, value)),
+                    "Reinstall" => actions.push((PackageAction::Reinstall, value)),
+                    "Remove" => actions.push((PackageAction::Remove, value)),
+                    "Purge" => actions.push((PackageAction::Purge, value)),
+                    _ => {}
+                }
+            }
+            //"name (uid)", apt only writes it when it runs under sudo
+            let user = requested_by.as_deref().and_then(|v| {
+                let uid = v.rsplit_once('(')?.1.trim_end_matches(')').parse::<u32>().ok()?;
+                find_user(system_info, uid)
+            });
+
+            for (action, packages) in actions {
+                for (package, versions) in split_apt_packages(packages) {
+                    let (package, architecture) = split_architecture(&package);
+                    //"version", "version, automatic" or "old version, new version"
+                    let versions: Vec<&str> = versions
+                        .split(", ")
+                        .filter(|v| *v != "automatic")
+                        .collect();
+                    let (old_version, version) = match (&action, versions.as_slice()) {
+                        (_, [old_version, version]) => (Some(old_version.to_string()), Some(version.to_string())),
+                        (PackageAction::Remove | PackageAction::Purge, [version]) => (Some(version.to_string()), None),
+                        (_, [version]) => (None, Some(version.to_string())),
+                        _ => (None, None),
+                    };
+                    self.events.push(PackageEvent {
+                        timestamp,
+                        action: action.clone(),
+                        package,
+                        architecture,
+                        old_version,
+                        version,
+                        source: HistorySource::Apt,
+                        path: path.to_path_buf(),
+                        commandline: commandline.clone(),
+                        requested_by: requested_by.clone(),
+                        user: user.clone(),
+                    });
+                }
+            }
+        }
+    }
+
+    fn process_apt_term_log(&mut self, contents: &str, path: &Path) {
+        let mut terminal_log: Option<AptTerminalLog> = None;
+        for line in contents.lines() {
+            if let Some(value) = line.strip_prefix("Log started: ") {
+                terminal_log = Some(AptTerminalLog {
+                    start: parse_date_time(value),
+                    path: path.to_path_buf(),
+                    ..Default::default()
+                });
+            } else if let Some(value) = line.strip_prefix("Log ended: ") {
+                if let Some(mut v) = terminal_log.take() {
+                    v.end = parse_date_time(value);
+                    self.terminal_logs.push(v);
+                }
+            } else if let Some(v) = terminal_log.as_mut() {
+                v.contents.push_str(line);
+                v.contents.push('\n');
+            }
+        }
+        //an interrupted apt run never writes Log ended
+        if let Some(v) = terminal_log {
+            self.terminal_logs.push(v);
+        }
+    }
+
+    //"Mon DD HH:MM:SS Action: [epoch:]name-version-release.arch", the year is not logged
+    fn process_yum_log(&mut self, contents: &str, path: &Path, modified: Option<usize>) {
+        let last_write = modified.and_then(|v| chrono::DateTime::from_timestamp(v as i64, 0));
+        for line in contents.lines() {
+            let (date, entry) = match (line.get(..15), line.get(16..)) {
+                (Some(date), Some(entry)) => (date, entry),
+                _ => continue,
+            };
+            let (action, package) = match entry.split_once(": ") {
+                Some(("Installed", v)) => (PackageAction::Install, v),
+                Some(("Updated", v)) => (PackageAction::Upgrade, v),
+                Some(("Erased", v)) => (PackageAction::Remove, v),
+                _ => continue,
+            };
+            //the log is written in order, dates after the last write belong to the previous year
+            let timestamp = last_write.and_then(|last_write| {
+                let timestamp = NaiveDateTime::parse_from_str(&format!("{} {}", last_write.year(), date), "%Y %b %d %H:%M:%S").ok()?;
+                match timestamp > last_write.naive_utc() {
+                    true => NaiveDateTime::parse_from_str(&format!("{} {}", last_write.year() - 1, date), "%Y %b %d %H:%M:%S").ok(),
+                    false => Some(timestamp),
+                }
+            });
+            let (package, version, architecture) = split_nevra(package.trim());
+            self.events.push(PackageEvent {
+                timestamp,
+                action,
+                package,
+                architecture,
+                version,
+                source: HistorySource::Yum,
+                path: path.to_path_buf(),
+                ..Default::default()
+            });
+        }
+    }
+
+    //transactions of dnf, the items replaced by an upgrade or downgrade are skipped
+    fn process_dnf_history(&mut self, vfs: &mut impl VirtualFileSystem, system_info: &SystemInfo) {
+        let rows = match with_sqlite_database(vfs, Path::new(DNF_HISTORY_PATH), |connection| {
+            let mut statement = connection.prepare(
+                "SELECT trans.dt_begin, trans.user_id, trans.cmdline, rpm.name, rpm.epoch, rpm.version, \
+                 rpm.release, rpm.arch, trans_item.action FROM trans_item \
+                 JOIN trans ON trans_item.trans_id = trans.id JOIN rpm ON trans_item.item_id = rpm.item_id \
+                 ORDER BY trans.id, trans_item.id",
+            )?;
+            let rows = statement.query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, u32>(8)?,
+                ))
+            })?;
+            rows.collect::<rusqlite::Result<Vec<_>>>()
+        }) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+
+        for (begin, user_id, commandline, name, epoch, version, release, arch, action) in rows {
+            let action = match action {
+                1 | 4 => PackageAction::Install,
+                2 => PackageAction::Downgrade,
+                6 => PackageAction::Upgrade,
+                8 | 5 => PackageAction::Remove,
+                9 => PackageAction::Reinstall,
+                _ => continue,
+            };
+            let version = match epoch {
+                0 => format!("{}-{}", version, release),
+                epoch => format!("{}:{}-{}", epoch, version, release),
+            };
+            self.events.push(PackageEvent {
+                timestamp: chrono::DateTime::from_timestamp(begin, 0).map(|v| v.naive_utc()),
+                action,
+                package: name,
+                architecture: Some(arch),
+                version: Some(version),
+                source: HistorySource::Dnf,
+                path: PathBuf::from(DNF_HISTORY_PATH),
+                commandline,
+                requested_by: Some(user_id.to_string()),
+                user: find_user(system_info, user_id),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+fn read_log(vfs: &mut impl VirtualFileSystem, path: &Path) -> Option<String> {
+    let contents = read_decompressed(vfs, path).ok()?;
+    Some(String::from_utf8_lossy(&contents).to_string())
+}
+
+//dpkg and apt separate the columns of the date with one or two spaces
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    let value = value.split_whitespace().collect::<Vec<&str>>().join(" ");
+    NaiveDateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .or_else(|| NaiveDate::parse_from_str(&value, "%Y-%m-%d").ok()?.and_hms_opt(0, 0, 0))
+}
+
+fn dpkg_version(value: &str) -> Option<String> {
+    match value {
+        "<none>" => None,
+        v => Some(v.to_string()),
+    }
+}
+
+fn split_architecture(value: &str) -> (String, Option<String>) {
+    match value.split_once(':') {
+        Some((package, architecture)) => (package.to_string(), Some(architecture.to_string())),
+        None => (value.to_string(), None),
+    }
+}
+
+//"name:arch (version), name:arch (version, automatic)"
+fn split_apt_packages(value: &str) -> Vec<(String, String)> {
+    value
+        .split("), ")
+        .filter_map(|v| {
+            let (package, versions) = v.split_once(" (")?;
+            Some((package.trim().to_string(), versions.trim_end_matches(')').to_string()))
+        })
+        .collect()
+}
+
+//yum logs the full name-[epoch:]version-release.arch for installs and updates and only the name for erases
+fn split_nevra(value: &str) -> (String, Option<String>, Option<String>) {
+    let (epoch, value) = match value.split_once(':') {
+        Some((epoch, value)) if epoch.chars().all(|c| c.is_ascii_digit()) => (Some(epoch), value),
+        _ => (None, value),
+    };
+    let (value, architecture) = match value.rsplit_once('.') {
+        Some((v, architecture)) if !architecture.contains('-') && value.contains('-') => (v, Some(architecture.to_string())),
+        _ => (value, None),
+    };
+    let mut parts = value.rsplitn(3, '-');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(release), Some(version), Some(name)) if architecture.is_some() => {
+            let version = match epoch {
+                Some(epoch) => format!("{}:{}-{}", epoch, version, release),
+                None => format!("{}-{}", version, release),
+            };
+            (name.to_string(), Some(version), architecture)
+        }
+        _ => (value.to_string(), None, None),
+    }
+}
+
+fn find_user(system_info: &SystemInfo, uid: u32) -> Option<UserInfo> {
+    system_info.users.iter().find(|v| v.id == uid).cloned()
+}
+
+#[cfg(test)]
+mod package_history_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_package_history() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let system_info = SystemInfo::load(&mut vfs).expect("Should load passwd");
+        let package_history = PackageHistory::load_package_history(&system_info, &mut vfs)
+            .expect("Should load package history");
+
+        //the gzipped rotation of the apt history
+        let nmap = package_history.events_for_package("nmap");
+        assert_eq!(1, nmap.len());
+        assert_eq!(PackageAction::Install, nmap[0].action);
+        assert_eq!(Some("7.91+dfsg1+really7.80+dfsg1-2ubuntu0.1"), nmap[0].version.as_deref());
+        assert_eq!(Some("apt-get install nmap gcc"), nmap[0].commandline.as_deref());
+        assert_eq!(Some("forensicrs (1000)"), nmap[0].requested_by.as_deref());
+        assert_eq!("forensicrs", nmap[0].user.as_ref().unwrap().name);
+        assert!(nmap[0].path.ends_with("history.log.1.gz"));
+
+        let socat: Vec<(&HistorySource, &PackageAction)> = package_history
+            .events_for_package("socat")
+            .iter()
+            .map(|v| (&v.source, &v.action))
+            .collect();
+        assert_eq!(
+            vec![
+                (&HistorySource::Apt, &PackageAction::Install),
+                (&HistorySource::Dpkg, &PackageAction::Install),
+                (&HistorySource::Yum, &PackageAction::Remove),
+            ],
+            socat
+        );
+
+        let openssl_upgrade = package_history
+            .events_for_package("openssl")
+            .into_iter()
+            .find(|v| v.source == HistorySource::Dpkg)
+            .unwrap();
+        assert_eq!(PackageAction::Upgrade, openssl_upgrade.action);
+        assert_eq!(Some("3.0.2-0ubuntu1.10"), openssl_upgrade.old_version.as_deref());
+        assert_eq!(
+            NaiveDate::from_ymd_opt(2024, 2, 20).unwrap().and_hms_opt(11, 2, 42),
+            openssl_upgrade.timestamp
+        );
+
+        let ncat = package_history.events_for_package("nmap-ncat");
+        assert_eq!(Some("2:7.70-6.el8"), ncat[0].version.as_deref());
+        assert_eq!(Some("x86_64"), ncat[0].architecture.as_deref());
+
+        let dnf: Vec<(&str, &PackageAction, Option<&str>)> = package_history
+            .events
+            .iter()
+            .filter(|v| v.source == HistorySource::Dnf)
+            .map(|v| (v.package.as_str(), &v.action, v.user.as_ref().map(|v| v.name.as_str())))
+            .collect();
+        assert_eq!(
+            vec![
+                ("gcc", &PackageAction::Install, Some("root")),
+                ("openssh-server", &PackageAction::Upgrade, Some("forensicrs")),
+            ],
+            dnf
+        );
+
+        assert_eq!(2, package_history.terminal_logs.len());
+        assert!(package_history.terminal_logs[1].contents.contains("Removing rsyslog"));
+    }
+}
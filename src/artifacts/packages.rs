@@ -0,0 +1,521 @@
+pub use crate::prelude::with_sqlite_database;
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+const DPKG_STATUS_PATH: &str = "/var/lib/dpkg/status";
+const DPKG_INFO_PATH: &str = "/var/lib/dpkg/info";
+const RPM_PATHS: [&str; 2] = ["/var/lib/rpm", "/usr/lib/sysimage/rpm"];
+
+//tags of the RPM header used by the inventory
+const RPMTAG_NAME: u32 = 1000;
+const RPMTAG_VERSION: u32 = 1001;
+const RPMTAG_RELEASE: u32 = 1002;
+const RPMTAG_INSTALLTIME: u32 = 1008;
+const RPMTAG_ARCH: u32 = 1022;
+const RPMTAG_OLDFILENAMES: u32 = 1027;
+const RPMTAG_FILEDIGESTS: u32 = 1035;
+const RPMTAG_DIRINDEXES: u32 = 1116;
+const RPMTAG_BASENAMES: u32 = 1117;
+const RPMTAG_DIRNAMES: u32 = 1118;
+const RPMTAG_FILEDIGESTALGO: u32 = 5011;
+
+//BerkeleyDB hash database used by rpm before 4.16
+const BDB_HASH_MAGIC: u32 = 0x061561;
+const BDB_PAGE_HASH_UNSORTED: u8 = 2;
+const BDB_PAGE_HASH: u8 = 13;
+const BDB_KEYDATA: u8 = 1;
+const BDB_OFFPAGE: u8 = 3;
+const BDB_PAGE_HEADER_SIZE: usize = 26;
+//NDB database of rpm 4.16 (SUSE), the magics are the little endian "RpmP", "Slot" and "BlbS"
+const NDB_HEADER_MAGIC: u32 = 0x506d7052;
+const NDB_SLOT_MAGIC: u32 = 0x746f6c53;
+const NDB_BLOB_MAGIC: u32 = 0x53626c42;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PackageSource {
+    #[default]
+    Dpkg,
+    Rpm,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum DigestAlgorithm {
+    #[default]
+    Md5,
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+    Unknown(u32),
+}
+
+//expected hash of a file installed by a package, in hexadecimal
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileDigest {
+    pub path: PathBuf,
+    pub algorithm: DigestAlgorithm,
+    pub digest: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Package {
+    pub name: String,
+    pub version: String,
+    pub architecture: String,
+    //dpkg Status field, empty for rpm packages
+    pub status: String,
+    pub source: PackageSource,
+    pub install_time: Option<i64>,
+    pub files: Vec<PathBuf>,
+    pub digests: Vec<FileDigest>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PackageInventory {
+    pub packages: Vec<Package>,
+    //packages that install each file, a file can be shared by several packages
+    pub file_owners: HashMap<PathBuf, Vec<String>>,
+}
+
+impl PackageInventory {
+    pub fn load_packages(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut inventory = Self::default();
+        inventory.process_dpkg_database(vfs);
+        inventory.process_rpm_databases(vfs);
+
+        for package in &inventory.packages {
+            for file in &package.files {
+                let owners = inventory.file_owners.entry(file.clone()).or_default();
+                if !owners.contains(&package.name) {
+                    owners.push(package.name.clone());
+                }
+            }
+        }
+        Ok(inventory)
+    }
+
+    //false when the evidence has no package database, then nothing can be called orphaned
+    pub fn has_packages(&self) -> bool {
+        !self.packages.is_empty()
+    }
+
+    pub fn owner(&self, path: &Path) -> Option<&str> {
+        self.file_owners
+            .get(path)
+            .and_then(|v| v.first())
+            .map(|v| v.as_str())
+    }
+
+    //a file not installed by any package on a system with a package database
+    pub fn is_orphaned(&self, path: &Path) -> bool {
+        self.has_packages() && !self.file_owners.contains_key(path)
+    }
+
+    pub fn get_package(&self, name: &str) -> Option<&Package> {
+        self.packages.iter().find(|v| v.name == name)
+    }
+
+    //status paragraphs plus the info/<package>.list and .md5sums files
+    fn process_dpkg_database(&mut self, vfs: &mut impl VirtualFileSystem) {
+        let status = match vfs.read_to_string(Path::new(DPKG_STATUS_PATH)) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        for paragraph in status.split("\n\n") {
+            let mut package = Package {
+                source: PackageSource::Dpkg,
+                ..Default::default()
+            };
+            for line in paragraph.lines() {
+                //continuation lines of multiline fields start with a space
+                if line.starts_with(' ') || line.starts_with('\t') {
+                    continue;
+                }
+                let (field, value) = match line.split_once(':') {
+                    Some((field, value)) => (field, value.trim().to_string()),
+                    None => continue,
+                };
+                match field {
+                    "Package" => package.name = value,
+                    "Version" => package.version = value,
+                    "Architecture" => package.architecture = value,
+                    "Status" => package.status = value,
+                    _ => {}
+                }
+            }
+            if package.name.is_empty() {
+                continue;
+            }
+
+            let info_path = Path::new(DPKG_INFO_PATH);
+            let multiarch_name = format!("{}:{}", package.name, package.architecture);
+            for info_name in [&package.name, &multiarch_name] {
+                if let Ok(list) = vfs.read_to_string(&info_path.join(format!("{}.list", info_name))) {
+                    package.files = list
+                        .lines()
+                        .filter(|v| !v.is_empty() && *v != "/.")
+                        .map(PathBuf::from)
+                        .collect();
+                }
+                if let Ok(md5sums) = vfs.read_to_string(&info_path.join(format!("{}.md5sums", info_name))) {
+                    package.digests = parse_md5sums(&md5sums);
+                }
+            }
+            self.packages.push(package);
+        }
+    }
+
+    //rpmdb.sqlite, Packages.db (NDB) and Packages (BerkeleyDB), stale databases left by conversions are read too
+    fn process_rpm_databases(&mut self, vfs: &mut impl VirtualFileSystem) {
+        let mut headers: Vec<Vec<u8>> = Vec::new();
+        for rpm_path in RPM_PATHS {
+            let rpm_path = Path::new(rpm_path);
+            if let Ok(blobs) = with_sqlite_database(vfs, &rpm_path.join("rpmdb.sqlite"), |connection| {
+                let mut statement = connection.prepare("SELECT blob FROM Packages ORDER BY hnum")?;
+                let rows = statement.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+                rows.collect::<rusqlite::Result<Vec<Vec<u8>>>>()
+            }) {
+                headers.extend(blobs);
+            }
+            if let Ok(contents) = vfs.read_all(&rpm_path.join("Packages.db")) {
+                headers.extend(read_ndb_blobs(&contents));
+            }
+            if let Ok(contents) = vfs.read_all(&rpm_path.join("Packages")) {
+                headers.extend(read_bdb_hash_values(&contents));
+            }
+        }
+
+        for header in headers {
+            let package = match parse_rpm_header(&header) {
+                Some(v) => v,
+                None => continue,
+            };
+            let duplicated = self.packages.iter().any(|v| {
+                v.source == PackageSource::Rpm
+                    && v.name == package.name
+                    && v.version == package.version
+                    && v.architecture == package.architecture
+            });
+            if !duplicated {
+                self.packages.push(package);
+            }
+        }
+    }
+}
+
+//"<md5>  <path without leading slash>" lines
+fn parse_md5sums(md5sums: &str) -> Vec<FileDigest> {
+    md5sums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .map(|(digest, path)| FileDigest {
+            path: Path::new("/").join(path.trim()),
+            algorithm: DigestAlgorithm::Md5,
+            digest: digest.to_lowercase(),
+        })
+        .collect()
+}
+
+//header blob without lead: entry count, data size, 16 byte entries (tag, type, offset, count) and the data
+pub fn parse_rpm_header(blob: &[u8]) -> Option<Package> {
+    let index_count = read_u32_be(blob, 0)? as usize;
+    let data_size = read_u32_be(blob, 4)? as usize;
+    let data_start = 8 + index_count.checked_mul(16)?;
+    let data = blob.get(data_start..data_start.checked_add(data_size)?)?;
+
+    let mut entries: HashMap<u32, (u32, usize, usize)> = HashMap::new();
+    for i in 0..index_count {
+        let entry = 8 + i * 16;
+        entries.insert(
+            read_u32_be(blob, entry)?,
+            (
+                read_u32_be(blob, entry + 4)?,
+                read_u32_be(blob, entry + 8)? as usize,
+                read_u32_be(blob, entry + 12)? as usize,
+            ),
+        );
+    }
+    let strings = |tag: u32| -> Vec<String> {
+        match entries.get(&tag) {
+            //STRING, STRING_ARRAY and I18NSTRING types
+            Some((6 | 8 | 9, offset, count)) => data
+                .get(*offset..)
+                .unwrap_or_default()
+                .split(|v| *v == 0)
+                .take(*count)
+                .map(|v| String::from_utf8_lossy(v).to_string())
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    let integers = |tag: u32| -> Vec<u32> {
+        match entries.get(&tag) {
+            //the count is not trusted, data cannot hold more integers than its size allows
+            Some((4, offset, count)) => (0..(*count).min(data.len() / 4))
+                .filter_map(|i| read_u32_be(data, offset + i * 4))
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+
+    let name = strings(RPMTAG_NAME).into_iter().next()?;
+    let version = strings(RPMTAG_VERSION).into_iter().next().unwrap_or_default();
+    let release = strings(RPMTAG_RELEASE).into_iter().next().unwrap_or_default();
+
+    let dir_names = strings(RPMTAG_DIRNAMES);
+    let files: Vec<PathBuf> = match entries.contains_key(&RPMTAG_BASENAMES) {
+        true => strings(RPMTAG_BASENAMES)
+            .iter()
+            .zip(integers(RPMTAG_DIRINDEXES))
+            .map(|(base_name, dir_index)| {
+                let dir_name = dir_names.get(dir_index as usize).cloned().unwrap_or_default();
+                PathBuf::from(format!("{}{}", dir_name, base_name))
+            })
+            .collect(),
+        false => strings(RPMTAG_OLDFILENAMES).into_iter().map(PathBuf::from).collect(),
+    };
+    let algorithm = match integers(RPMTAG_FILEDIGESTALGO).first() {
+        None | Some(1) => DigestAlgorithm::Md5,
+        Some(2) => DigestAlgorithm::Sha1,
+        Some(8) => DigestAlgorithm::Sha256,
+        Some(9) => DigestAlgorithm::Sha384,
+        Some(10) => DigestAlgorithm::Sha512,
+        Some(v) => DigestAlgorithm::Unknown(*v),
+    };
+    //directories and ghost files have an empty digest
+    let digests = files
+        .iter()
+        .zip(strings(RPMTAG_FILEDIGESTS))
+        .filter(|(_, digest)| !digest.is_empty())
+        .map(|(path, digest)| FileDigest {
+            path: path.clone(),
+            algorithm: algorithm.clone(),
+            digest,
+        })
+        .collect();
+
+    Some(Package {
+        name,
+        version: match release.is_empty() {
+            true => version,
+            false => format!("{}-{}", version, release),
+        },
+        architecture: strings(RPMTAG_ARCH).into_iter().next().unwrap_or_default(),
+        status: String::new(),
+        source: PackageSource::Rpm,
+        install_time: integers(RPMTAG_INSTALLTIME).first().map(|v| *v as i64),
+        files,
+        digests,
+    })
+}
+
+//data items of the hash pages, keys and values alternate and big values live in overflow pages
+pub fn read_bdb_hash_values(contents: &[u8]) -> Vec<Vec<u8>> {
+    let mut values = Vec::new();
+    let big_endian = match (read_u32_le(contents, 12), read_u32_be(contents, 12)) {
+        (Some(BDB_HASH_MAGIC), _) => false,
+        (_, Some(BDB_HASH_MAGIC)) => true,
+        _ => return values,
+    };
+    let read_u16 = |offset: usize| -> Option<usize> {
+        let bytes: [u8; 2] = contents.get(offset..offset + 2)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        } as usize)
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        match big_endian {
+            true => read_u32_be(contents, offset),
+            false => read_u32_le(contents, offset),
+        }
+    };
+    let page_size = match read_u32(20) {
+        Some(v) if v >= 512 => v as usize,
+        _ => return values,
+    };
+
+    for page_start in (page_size..contents.len()).step_by(page_size) {
+        let page_type = contents.get(page_start + 25).copied().unwrap_or_default();
+        if page_type != BDB_PAGE_HASH && page_type != BDB_PAGE_HASH_UNSORTED {
+            continue;
+        }
+        let entries = read_u16(page_start + 20).unwrap_or_default();
+        for item in (1..entries).step_by(2) {
+            let item_offset = match read_u16(page_start + BDB_PAGE_HEADER_SIZE + item * 2) {
+                Some(v) => page_start + v,
+                None => continue,
+            };
+            //items are stored from the end of the page, each one ends where the previous one starts
+            let item_end = match read_u16(page_start + BDB_PAGE_HEADER_SIZE + (item - 1) * 2) {
+                Some(v) => page_start + v,
+                None => continue,
+            };
+            match contents.get(item_offset).copied() {
+                Some(BDB_KEYDATA) if item_end > item_offset => {
+                    if let Some(v) = contents.get(item_offset + 1..item_end) {
+                        values.push(v.to_vec());
+                    }
+                }
+                Some(BDB_OFFPAGE) => {
+                    let (page, length) = match (read_u32(item_offset + 4), read_u32(item_offset + 8)) {
+                        (Some(page), Some(length)) => (page as usize, length as usize),
+                        _ => continue,
+                    };
+                    values.push(read_bdb_overflow(contents, page_size, page, length, &read_u16, &read_u32));
+                }
+                _ => {}
+            }
+        }
+    }
+    values
+}
+
+//overflow pages keep the used length in the free area offset field and are chained by next page
+fn read_bdb_overflow(
+    contents: &[u8],
+    page_size: usize,
+    mut page: usize,
+    length: usize,
+    read_u16: &dyn Fn(usize) -> Option<usize>,
+    read_u32: &dyn Fn(usize) -> Option<u32>,
+) -> Vec<u8> {
+    //the length is read from the item, the database cannot hold more than its own size
+    let mut value = Vec::with_capacity(length.min(contents.len()));
+    let mut visited = 0;
+    while page != 0 && value.len() < length && visited < contents.len() / page_size {
+        let page_start = page * page_size;
+        let used = read_u16(page_start + 22).unwrap_or_default();
+        match contents.get(page_start + BDB_PAGE_HEADER_SIZE..page_start + BDB_PAGE_HEADER_SIZE + used) {
+            Some(v) => value.extend_from_slice(v),
+            None => break,
+        }
+        page = read_u32(page_start + 16).unwrap_or_default() as usize;
+        visited += 1;
+    }
+    value.truncate(length);
+    value
+}
+
+//slots of 16 bytes after the 32 byte header point to blobs stored in 16 byte blocks
+pub fn read_ndb_blobs(contents: &[u8]) -> Vec<Vec<u8>> {
+    let mut blobs = Vec::new();
+    if read_u32_le(contents, 0) != Some(NDB_HEADER_MAGIC) {
+        return blobs;
+    }
+    let slot_pages = read_u32_le(contents, 12).unwrap_or_default() as usize;
+    //the slot pages can not be larger than the file, and a blob pointed by several slots is read once
+    let slot_count = slot_pages
+        .saturating_mul(4096 / 16)
+        .saturating_sub(2)
+        .min(contents.len().saturating_sub(32) / 16);
+    let mut read_blocks = HashSet::new();
+    for slot in 0..slot_count {
+        let slot_offset = 32 + slot * 16;
+        if read_u32_le(contents, slot_offset) != Some(NDB_SLOT_MAGIC) {
+            continue;
+        }
+        let package_index = read_u32_le(contents, slot_offset + 4).unwrap_or_default();
+        let block_offset = read_u32_le(contents, slot_offset + 8).unwrap_or_default() as usize * 16;
+        if package_index == 0
+            || read_u32_le(contents, block_offset) != Some(NDB_BLOB_MAGIC)
+            || !read_blocks.insert(block_offset)
+        {
+            continue;
+        }
+        let blob_length = read_u32_le(contents, block_offset + 12).unwrap_or_default() as usize;
+        if let Some(v) = contents.get(block_offset + 16..block_offset + 16 + blob_length) {
+            blobs.push(v.to_vec());
+        }
+    }
+    blobs
+}
+
+fn read_u32_be(contents: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(contents.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u32_le(contents: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(contents.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod packages_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_dpkg_and_rpm_packages() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let inventory = PackageInventory::load_packages(&mut vfs).expect("Should load packages");
+
+        let rsyslog = inventory.get_package("rsyslog").unwrap();
+        assert_eq!("8.2112.0-2ubuntu2.2", rsyslog.version);
+        assert_eq!("install ok installed", rsyslog.status);
+        assert_eq!(Some("rsyslog"), inventory.owner(Path::new("/etc/init.d/rsyslog")));
+        assert_eq!(
+            Some("coreutils"),
+            inventory.owner(Path::new("/usr/bin/ls"))
+        );
+        let ls_digest = inventory
+            .get_package("coreutils")
+            .unwrap()
+            .digests
+            .iter()
+            .find(|v| v.path == Path::new("/usr/bin/ls"))
+            .unwrap();
+        assert_eq!(DigestAlgorithm::Md5, ls_digest.algorithm);
+        assert!(inventory.is_orphaned(Path::new("/etc/init.d/apache2")));
+
+        //one rpm package from each database format
+        let openssh = inventory.get_package("openssh-server").unwrap();
+        assert_eq!(PackageSource::Rpm, openssh.source);
+        assert_eq!("8.7p1-34.el9", openssh.version);
+        assert_eq!(Some(1700000000), openssh.install_time);
+        assert_eq!(
+            vec![PathBuf::from("/etc/ssh/sshd_config"), PathBuf::from("/usr/sbin/sshd")],
+            openssh.files
+        );
+        //the configuration file has no digest in the fixture
+        assert_eq!(1, openssh.digests.len());
+        assert_eq!(DigestAlgorithm::Sha256, openssh.digests[0].algorithm);
+        assert_eq!(Path::new("/usr/sbin/sshd"), openssh.digests[0].path);
+        assert_eq!("x86_64", inventory.get_package("procps-ng").unwrap().architecture);
+        assert_eq!(
+            vec![PathBuf::from("/usr/bin/ls"), PathBuf::from("/usr/bin/dir")],
+            inventory.get_package("coreutils-single").unwrap().files
+        );
+        assert_eq!(
+            vec!["coreutils".to_string(), "coreutils-single".to_string()],
+            inventory.file_owners[Path::new("/usr/bin/ls")]
+        );
+    }
+
+    #[test]
+    fn should_bound_ndb_slots_by_the_file() {
+        let mut contents = vec![0u8; 128];
+        contents[0..4].copy_from_slice(&NDB_HEADER_MAGIC.to_le_bytes());
+        contents[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        //two slots pointing to the same blob at block 4
+        for slot_offset in [32, 48] {
+            contents[slot_offset..slot_offset + 4].copy_from_slice(&NDB_SLOT_MAGIC.to_le_bytes());
+            contents[slot_offset + 4..slot_offset + 8].copy_from_slice(&1u32.to_le_bytes());
+            contents[slot_offset + 8..slot_offset + 12].copy_from_slice(&4u32.to_le_bytes());
+        }
+        contents[64..68].copy_from_slice(&NDB_BLOB_MAGIC.to_le_bytes());
+        contents[76..80].copy_from_slice(&4u32.to_le_bytes());
+        contents[80..84].copy_from_slice(b"blob");
+        assert_eq!(vec![b"blob".to_vec()], read_ndb_blobs(&contents));
+    }
+}
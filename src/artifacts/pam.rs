@@ -0,0 +1,362 @@
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//directories where Linux-PAM loads modules given without absolute path
+const PAM_MODULE_DIRS: [&str; 4] = ["/lib/security", "/lib64/security", "/usr/lib/security", "/usr/lib64/security"];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PamControl {
+    #[default]
+    Required,
+    Requisite,
+    Sufficient,
+    Optional,
+    Include,
+    Substack,
+    //[value=action ...] syntax
+    Bracket(Vec<(String, String)>),
+    Unknown(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PamEntry {
+    pub service: String,
+    //auth, account, password or session, empty for @include lines
+    pub module_type: String,
+    //a leading - makes PAM skip the line when the module is missing
+    pub silent_if_missing: bool,
+    pub control: PamControl,
+    //module, or included file for include, substack and @include
+    pub module: String,
+    pub arguments: Vec<String>,
+    pub resolved_path: Option<PathBuf>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PamFindingKind {
+    #[default]
+    ExecModule,
+    ScriptModule,
+    ModuleOutsideSecurityDir,
+    PermitInAuth,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PamFinding {
+    pub kind: PamFindingKind,
+    pub description: String,
+    pub entry: PamEntry,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PamConfig {
+    pub files: Vec<PathBuf>,
+    pub entries: Vec<PamEntry>,
+    pub findings: Vec<PamFinding>,
+}
+
+impl PamConfig {
+    pub fn load_pam_config(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut pam_config = Self::default();
+        let module_dirs = get_module_dirs(vfs);
+
+        //PAM ignores pam.conf when pam.d exists, both are read because either can be tampered
+        pam_config.process_pam_file(vfs, Path::new("/etc/pam.conf"), None);
+        let pamd_path = PathBuf::from("/etc/pam.d");
+        if let Ok(mut services) = vfs.read_dir(&pamd_path) {
+            services.sort_by_key(|v| v.to_string());
+            for service in services {
+                let service_name = match service {
+                    forensic_rs::traits::vfs::VDirEntry::Directory(_) => continue,
+                    other => other.to_string(),
+                };
+                pam_config.process_pam_file(vfs, &pamd_path.join(&service_name), Some(&service_name));
+            }
+        }
+
+        for entry in &mut pam_config.entries {
+            if !matches!(entry.control, PamControl::Include | PamControl::Substack) && !entry.module.is_empty() {
+                entry.resolved_path = resolve_module(vfs, &module_dirs, &entry.module);
+            }
+        }
+        pam_config.findings = pam_config.entries.iter().flat_map(check_entry).collect();
+        Ok(pam_config)
+    }
+
+    //entries of pam.conf start with the service name, the ones of pam.d files take it from the file name
+    fn process_pam_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path, service: Option<&str>) {
+        let pam_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        self.files.push(path.to_path_buf());
+
+        let mut line = String::new();
+        let mut first_line_number = 0;
+        for (line_number, physical_line) in pam_file.lines().enumerate() {
+            if line.is_empty() {
+                first_line_number = line_number + 1;
+            }
+            match physical_line.strip_suffix('\\') {
+                Some(v) => {
+                    line.push_str(v);
+                    line.push(' ');
+                    continue;
+                }
+                None => line.push_str(physical_line),
+            }
+            let logical_line = std::mem::take(&mut line);
+            let tokens = split_pam_line(logical_line.split('#').next().unwrap_or_default());
+            if let Some(entry) = parse_pam_tokens(tokens, service, path, first_line_number) {
+                self.entries.push(entry);
+            }
+        }
+    }
+}
+
+fn parse_pam_tokens(mut tokens: Vec<String>, service: Option<&str>, path: &Path, line_number: usize) -> Option<PamEntry> {
+    let service = match service {
+        Some(v) => v.to_string(),
+        None if tokens.is_empty() => return None,
+        None => tokens.remove(0),
+    };
+    let mut entry = PamEntry {
+        service,
+        path: path.to_path_buf(),
+        line_number,
+        ..Default::default()
+    };
+    let first = tokens.first()?.clone();
+    if first == "@include" {
+        entry.control = PamControl::Include;
+        entry.module = tokens.get(1)?.clone();
+        return Some(entry);
+    }
+    if tokens.len() < 3 {
+        return None;
+    }
+    entry.silent_if_missing = first.starts_with('-');
+    entry.module_type = first.trim_start_matches('-').to_lowercase();
+    entry.control = parse_control(&tokens[1]);
+    entry.module = tokens[2].clone();
+    //brackets only group an argument with spaces, they are not part of it
+    entry.arguments = tokens[3..]
+        .iter()
+        .map(|v| v.strip_prefix('[').and_then(|v| v.strip_suffix(']')).unwrap_or(v).to_string())
+        .collect();
+    Some(entry)
+}
+
+fn parse_control(control: &str) -> PamControl {
+    if let Some(values) = control.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return PamControl::Bracket(
+            values
+                .split_whitespace()
+                .map(|v| match v.split_once('=') {
+                    Some((value, action)) => (value.to_string(), action.to_string()),
+                    None => (v.to_string(), String::new()),
+                })
+                .collect(),
+        );
+    }
+    match control.to_lowercase().as_str() {
+        "required" => PamControl::Required,
+        "requisite" => PamControl::Requisite,
+        "sufficient" => PamControl::Sufficient,
+        "optional" => PamControl::Optional,
+        "include" => PamControl::Include,
+        "substack" => PamControl::Substack,
+        _ => PamControl::Unknown(control.to_string()),
+    }
+}
+
+//splits on whitespace keeping [...] groups, used by controls and arguments with spaces, as one token
+fn split_pam_line(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_brackets = false;
+    for character in line.chars() {
+        match character {
+            '[' if token.is_empty() => {
+                in_brackets = true;
+                token.push(character);
+            }
+            ']' if in_brackets => {
+                in_brackets = false;
+                token.push(character);
+            }
+            v if v.is_whitespace() && !in_brackets => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            v => token.push(v),
+        }
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    tokens
+}
+
+//standard module directories plus the multiarch ones like /lib/x86_64-linux-gnu/security
+fn get_module_dirs(vfs: &mut impl VirtualFileSystem) -> Vec<PathBuf> {
+    let mut module_dirs: Vec<PathBuf> = Vec::new();
+    for lib_dir in ["/lib", "/usr/lib"] {
+        if let Ok(mut entries) = vfs.read_dir(Path::new(lib_dir)) {
+            entries.sort_by_key(|v| v.to_string());
+            for entry in entries {
+                if let forensic_rs::traits::vfs::VDirEntry::Directory(name) = entry {
+                    if name.contains("-linux-") {
+                        module_dirs.push(Path::new(lib_dir).join(name).join("security"));
+                    }
+                }
+            }
+        }
+    }
+    module_dirs.extend(PAM_MODULE_DIRS.iter().map(PathBuf::from));
+    module_dirs
+}
+
+fn resolve_module(vfs: &mut impl VirtualFileSystem, module_dirs: &[PathBuf], module: &str) -> Option<PathBuf> {
+    if module.starts_with('/') {
+        let module_path = PathBuf::from(module);
+        return vfs.metadata(&module_path).ok().map(|_| module_path);
+    }
+    module_dirs
+        .iter()
+        .map(|v| v.join(module))
+        .find(|v| vfs.metadata(v).is_ok())
+}
+
+fn is_security_dir(module_path: &Path) -> bool {
+    let module_dir = module_path.parent().unwrap_or(Path::new("/"));
+    let module_dir = module_dir.to_string_lossy();
+    let mut components = module_dir.trim_start_matches('/').split('/');
+    let lib = match components.next() {
+        Some("usr") => components.next(),
+        other => other,
+    };
+    if !lib.unwrap_or_default().starts_with("lib") {
+        return false;
+    }
+    match (components.next(), components.next(), components.next()) {
+        (Some("security"), None, None) => true,
+        (Some(multiarch), Some("security"), None) => multiarch.contains("-linux-"),
+        _ => false,
+    }
+}
+
+fn check_entry(entry: &PamEntry) -> Vec<PamFinding> {
+    let mut findings = Vec::new();
+    if matches!(entry.control, PamControl::Include | PamControl::Substack) {
+        return findings;
+    }
+    let module_name = entry.module.rsplit('/').next().unwrap_or_default();
+    let mut add = |kind: PamFindingKind, description: String| {
+        findings.push(PamFinding {
+            kind,
+            description,
+            entry: entry.clone(),
+        })
+    };
+    if module_name == "pam_exec.so" {
+        add(PamFindingKind::ExecModule, format!("pam_exec runs {}", entry.arguments.join(" ")));
+    }
+    if module_name == "pam_script.so" {
+        add(PamFindingKind::ScriptModule, "pam_script runs the scripts of its directory".to_string());
+    }
+    //modules that could not be resolved are only checked when the configuration gives an absolute path
+    let module_path = entry
+        .resolved_path
+        .clone()
+        .or_else(|| Some(PathBuf::from(&entry.module)).filter(|v| v.is_absolute()));
+    if let Some(module_path) = module_path {
+        if !is_security_dir(&module_path) {
+            add(PamFindingKind::ModuleOutsideSecurityDir,
+                format!("module loaded from {}", module_path.display()));
+        }
+    }
+    if module_name == "pam_permit.so" && entry.module_type == "auth" {
+        add(PamFindingKind::PermitInAuth, "pam_permit accepts any authentication".to_string());
+    }
+    findings
+}
+
+#[cfg(test)]
+mod pam_tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_pam_config_and_flag_modules() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let pam_config = PamConfig::load_pam_config(&mut vfs).expect("Should load PAM config");
+
+        let unix_entry = pam_config
+            .entries
+            .iter()
+            .find(|v| v.service == "common-auth" && v.module == "pam_unix.so")
+            .unwrap();
+        let unix_entry_test = PamEntry {
+            service: "common-auth".to_string(),
+            module_type: "auth".to_string(),
+            silent_if_missing: false,
+            control: PamControl::Bracket(vec![
+                ("success".to_string(), "1".to_string()),
+                ("default".to_string(), "ignore".to_string()),
+            ]),
+            module: "pam_unix.so".to_string(),
+            arguments: vec!["nullok".to_string()],
+            resolved_path: Some(PathBuf::from("/lib/x86_64-linux-gnu/security/pam_unix.so")),
+            path: PathBuf::from("/etc/pam.d/common-auth"),
+            line_number: 3,
+        };
+        assert_eq!(&unix_entry_test, unix_entry);
+
+        let sshd_services: Vec<(&str, &PamControl)> = pam_config
+            .entries
+            .iter()
+            .filter(|v| v.service == "sshd")
+            .map(|v| (v.module.as_str(), &v.control))
+            .collect();
+        assert_eq!(
+            vec![
+                ("common-auth", &PamControl::Include),
+                ("pam_exec.so", &PamControl::Optional),
+                ("pam_systemd.so", &PamControl::Optional),
+            ],
+            sshd_services
+        );
+        let exec_entry = pam_config.entries.iter().find(|v| v.module == "pam_exec.so").unwrap();
+        assert_eq!(vec!["quiet", "expose_authtok", "/usr/local/bin/log in.sh"], exec_entry.arguments);
+        assert!(pam_config.entries.iter().any(|v| v.service == "sshd" && v.silent_if_missing));
+
+        let findings: Vec<(&str, &PamFindingKind)> = pam_config
+            .findings
+            .iter()
+            .map(|v| (v.entry.module.as_str(), &v.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("pam_permit.so", &PamFindingKind::PermitInAuth),
+                ("pam_exec.so", &PamFindingKind::ExecModule),
+                ("/tmp/.x/pam_unix.so", &PamFindingKind::ModuleOutsideSecurityDir),
+            ],
+            findings
+        );
+        assert_eq!("login", pam_config.entries[0].service);
+    }
+}
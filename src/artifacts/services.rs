@@ -1,4 +1,4 @@
-pub use crate::prelude::{known_hosts, UserInfo};
+pub use crate::prelude::{known_hosts, packages::PackageInventory, UserInfo};
 pub use crate::ChRootFileSystem;
 use configparser::ini::Ini;
 pub use forensic_rs::traits::vfs;
@@ -14,26 +14,53 @@ pub use std::{
     path::{Path, PathBuf},
 };
 
+//fields of the ### BEGIN INIT INFO block of a SysV script
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LsbHeader {
+    pub provides: Vec<String>,
+    pub required_start: Vec<String>,
+    pub default_start: Vec<String>,
+    pub short_description: String,
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct InitdService {
     pub service_name: String,
     pub service_script: String,
+    pub lsb_header: Option<LsbHeader>,
+    pub daemon: Option<String>,
+    pub name: Option<String>,
+    pub start_stop_commands: Vec<String>,
+    //runlevels with a /etc/rcN.d/SNNname link to the script
+    pub enabled_runlevels: Vec<String>,
+    pub package: Option<String>,
+    //scripts without LSB header or not installed by any package
+    pub highlighted: bool,
 }
 
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct SystemdService {
     pub service_name: String,
     pub config: HashMap<String, HashMap<String, Option<String>>>,
+    pub unit_path: PathBuf,
+    //absolute programs of the ExecStart lines
+    pub executables: Vec<PathBuf>,
+    pub package: Option<String>,
+    //units or programs not installed by any package
+    pub highlighted: bool,
 }
 
 impl InitdService {
     pub fn process_init_services_files(
         vfs: &mut impl VirtualFileSystem,
+        package_inventory: &PackageInventory,
     ) -> ForensicResult<Vec<InitdService>> {
         let initd_path = PathBuf::from("/etc/init.d");
         let mut new_services: Vec<InitdService> = Vec::new();
+        let start_links = Self::get_start_links(vfs);
 
-        if let Ok(services) = vfs.read_dir(&initd_path) {
+        if let Ok(mut services) = vfs.read_dir(&initd_path) {
+            services.sort_by_key(|v| v.to_string());
             for service in services {
                 let file_name = match service {
                     forensic_rs::traits::vfs::VDirEntry::File(file_name) => file_name,
@@ -44,21 +71,122 @@ impl InitdService {
                     Err(_) => "".to_string(),
                 };
 
-                let new_service = InitdService {
+                let mut new_service = InitdService {
                     service_name: file_name,
                     service_script: service_script,
+                    ..Default::default()
                 };
+                new_service.parse_script();
+                new_service.enabled_runlevels = start_links
+                    .iter()
+                    .filter(|(_, name)| *name == new_service.service_name)
+                    .map(|(runlevel, _)| runlevel.clone())
+                    .collect();
+                let script_path = initd_path.join(&new_service.service_name);
+                new_service.package = package_inventory.owner(&script_path).map(|v| v.to_string());
+                new_service.highlighted =
+                    new_service.lsb_header.is_none() || package_inventory.is_orphaned(&script_path);
 
                 new_services.push(new_service);
             }
         }
         Ok(new_services)
     }
+
+    //fills the LSB header, the DAEMON and NAME variables and the start-stop-daemon calls
+    pub fn parse_script(&mut self) {
+        let mut lsb_header: Option<LsbHeader> = None;
+        let mut in_header = false;
+        let mut command = String::new();
+
+        for line in self.service_script.lines() {
+            let trimmed = line.trim();
+            if trimmed == "### BEGIN INIT INFO" {
+                in_header = true;
+                lsb_header = Some(LsbHeader::default());
+                continue;
+            }
+            if trimmed == "### END INIT INFO" {
+                in_header = false;
+                continue;
+            }
+            if in_header {
+                if let (Some(header), Some((key, value))) = (
+                    lsb_header.as_mut(),
+                    trimmed.trim_start_matches('#').split_once(':'),
+                ) {
+                    let values = value.split_whitespace().map(|v| v.to_string()).collect();
+                    match key.trim() {
+                        "Provides" => header.provides = values,
+                        "Required-Start" => header.required_start = values,
+                        "Default-Start" => header.default_start = values,
+                        "Short-Description" => header.short_description = value.trim().to_string(),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            if trimmed.starts_with('#') {
+                continue;
+            }
+
+            //start-stop-daemon calls are usually split with trailing backslashes
+            if !command.is_empty() || trimmed.contains("start-stop-daemon") {
+                command.push_str(trimmed.trim_end_matches('\\').trim());
+                if trimmed.ends_with('\\') {
+                    command.push(' ');
+                } else {
+                    self.start_stop_commands.push(std::mem::take(&mut command));
+                }
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("DAEMON=") {
+                self.daemon = Some(unquote(value));
+            } else if let Some(value) = trimmed.strip_prefix("NAME=") {
+                self.name = Some(unquote(value));
+            }
+        }
+        if !command.is_empty() {
+            self.start_stop_commands.push(command);
+        }
+        self.lsb_header = lsb_header;
+    }
+
+    //(runlevel, script name) of every S link in /etc/rc?.d
+    fn get_start_links(vfs: &mut impl VirtualFileSystem) -> Vec<(String, String)> {
+        let mut start_links = Vec::new();
+        for runlevel in ["0", "1", "2", "3", "4", "5", "6", "S"] {
+            let entries = match vfs.read_dir(&PathBuf::from(format!("/etc/rc{}.d", runlevel))) {
+                Ok(v) => v,
+                Err(_e) => continue,
+            };
+            for entry in entries {
+                let link_name = entry.to_string();
+                //names are checked by bytes, the evidence can hold names that are not ascii
+                let script_name = match link_name.strip_prefix('S') {
+                    Some(v) if v.as_bytes().get(..2).is_some_and(|b| b.iter().all(u8::is_ascii_digit)) => {
+                        match v.get(2..).filter(|name| !name.is_empty()) {
+                            Some(name) => name,
+                            None => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                start_links.push((runlevel.to_string(), script_name.to_string()));
+            }
+        }
+        start_links
+    }
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches(|c| c == '"' || c == '\'').to_string()
 }
 
 impl SystemdService {
     pub fn process_services_files(
         vfs: &mut impl VirtualFileSystem,
+        package_inventory: &PackageInventory,
     ) -> ForensicResult<Vec<SystemdService>> {
         let mut config = Ini::new();
         let services_paths = Self::get_services_paths();
@@ -77,7 +205,7 @@ impl SystemdService {
                     } else if let forensic_rs::traits::vfs::VDirEntry::Directory(dir_name) = service
                     {
                         //si se trata de un directorio se procesa los ficheros que tenga de servicios
-                        if let Ok(files) = vfs.read_dir(&path.join(dir_name)) {
+                        if let Ok(files) = vfs.read_dir(&path.join(&dir_name)) {
                             for file in files {
                                 if let forensic_rs::traits::vfs::VDirEntry::File(
                                     mut dir_service_name,
@@ -85,7 +213,7 @@ impl SystemdService {
                                 {
                                     services_vec.push(Self::insert_new_services(
                                         vfs,
-                                        &path,
+                                        &path.join(&dir_name),
                                         &mut dir_service_name,
                                         &mut config,
                                     )?);
@@ -99,9 +227,37 @@ impl SystemdService {
             }
         }
 
+        for service in services_vec.iter_mut().filter(|v| !v.service_name.is_empty()) {
+            service.tag_package(package_inventory);
+        }
+
         Ok(services_vec)
     }
 
+    //the unit file and the programs it starts are checked against the package database
+    pub fn tag_package(&mut self, package_inventory: &PackageInventory) {
+        self.package = package_inventory.owner(&self.unit_path).map(|v| v.to_string());
+        self.highlighted = package_inventory.is_orphaned(&self.unit_path)
+            || self.executables.iter().any(|v| package_inventory.is_orphaned(v));
+    }
+
+    fn exec_start_programs(config: &HashMap<String, HashMap<String, Option<String>>>) -> Vec<PathBuf> {
+        let exec_start = config
+            .get("service")
+            .and_then(|v| v.get("execstart"))
+            .and_then(|v| v.as_deref())
+            .unwrap_or_default();
+        //"-", "@", ":", "+" and "!" only change how systemd runs the program
+        exec_start
+            .split_whitespace()
+            .next()
+            .map(|v| v.trim_start_matches(|c| "-@:+!".contains(c)))
+            .filter(|v| v.starts_with('/'))
+            .map(PathBuf::from)
+            .into_iter()
+            .collect()
+    }
+
     pub fn insert_new_services(
         vfs: &mut impl VirtualFileSystem,
         path: &Path,
@@ -118,12 +274,16 @@ impl SystemdService {
 
             let file = config.read(service_script);
 
+            let config = match file {
+                Ok(config) => config,
+                Err(e) => return Err(forensic_rs::prelude::ForensicError::Other(e)),
+            };
             new_service = SystemdService {
                 service_name: file_name.to_string(),
-                config: match file {
-                    Ok(config) => config,
-                    Err(e) => return Err(forensic_rs::prelude::ForensicError::Other(e)),
-                },
+                unit_path: path.join(&file_name),
+                executables: Self::exec_start_programs(&config),
+                config,
+                ..Default::default()
             };
         }
 
@@ -150,14 +310,15 @@ impl SystemdService {
     }
 }
 
+#[cfg(test)]
 mod services_tests {
-    pub use std::{collections::HashMap, path::Path};
+    use std::{collections::HashMap, path::{Path, PathBuf}};
 
-    pub use forensic_rs::core::fs::StdVirtualFS;
+    use forensic_rs::core::fs::StdVirtualFS;
 
-    pub use crate::ChRootFileSystem;
+    use crate::ChRootFileSystem;
 
-    pub use super::{InitdService, SystemdService};
+    use super::{InitdService, LsbHeader, PackageInventory, SystemdService};
 
     #[test]
     fn should_process_initd_services() {
@@ -165,14 +326,17 @@ mod services_tests {
         let virtual_file_system = &Path::new(&base_path).join("artifacts");
         let mut _std_vfs = StdVirtualFS::new();
         let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
 
-        let initd_services = InitdService::process_init_services_files(&mut vfs);
+        let initd_services = InitdService::process_init_services_files(&mut vfs, &package_inventory);
 
         match initd_services {
             Ok(initd_service) => {
                 let initd_service_test = InitdService {
                     service_name: "apache2".to_string(),
                     service_script: "hola".to_string(),
+                    highlighted: true,
+                    ..Default::default()
                 };
                 assert_eq!(initd_service_test, initd_service[0]);
             }
@@ -182,22 +346,73 @@ mod services_tests {
         }
     }
 
+    #[test]
+    fn should_parse_initd_lsb_header_and_commands() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
+
+        let initd_services = InitdService::process_init_services_files(&mut vfs, &package_inventory).expect("Should process init.d");
+        let rsyslog = initd_services.iter().find(|v| v.service_name == "rsyslog").unwrap();
+
+        let lsb_header_test = LsbHeader {
+            provides: vec!["rsyslog".to_string()],
+            required_start: vec!["$remote_fs".to_string(), "$time".to_string()],
+            default_start: vec!["2".to_string(), "3".to_string(), "4".to_string(), "5".to_string()],
+            short_description: "enhanced syslogd".to_string(),
+        };
+        assert_eq!(Some(lsb_header_test), rsyslog.lsb_header);
+        assert_eq!(Some("/usr/sbin/rsyslogd".to_string()), rsyslog.daemon);
+        assert_eq!(Some("rsyslog".to_string()), rsyslog.name);
+        assert_eq!(
+            vec![
+                "start-stop-daemon --start --quiet --pidfile $PIDFILE --exec $DAEMON -- $RSYSLOGD_OPTIONS".to_string(),
+                "start-stop-daemon --stop --quiet --retry=TERM/30/KILL/5 --pidfile $PIDFILE --exec $DAEMON".to_string(),
+            ],
+            rsyslog.start_stop_commands
+        );
+        assert_eq!(vec!["2", "3", "4", "5"], rsyslog.enabled_runlevels);
+        assert_eq!(Some("rsyslog".to_string()), rsyslog.package);
+        assert!(!rsyslog.highlighted);
+
+        let bluetooth = initd_services.iter().find(|v| v.service_name == "bluetooth").unwrap();
+        assert!(bluetooth.highlighted);
+        assert!(bluetooth.enabled_runlevels.is_empty());
+
+        //S1évil has a single digit, the second byte is the start of "é"
+        let start_links = InitdService::get_start_links(&mut vfs);
+        assert_eq!(4, start_links.len());
+        assert!(start_links.iter().all(|(_, script_name)| script_name == "rsyslog"));
+    }
+
     #[test]
     fn should_process_systemd_services() {
         let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
         let virtual_file_system = &Path::new(&base_path).join("artifacts");
         let mut _std_vfs = StdVirtualFS::new();
         let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
 
-        let systemd_services = SystemdService::process_services_files(&mut vfs);
+        let systemd_services = SystemdService::process_services_files(&mut vfs, &package_inventory);
 
         match systemd_services {
             Ok(systemd_service) => {
-                let systemd_service_test = SystemdService {
-                    service_name: "tortuga.service".to_string(),
-                    config: HashMap::new(),
-                };
-                assert_eq!(systemd_service_test, systemd_service[2]);
+                let tortuga = systemd_service.iter().find(|v| v.service_name == "tortuga.service").unwrap();
+                assert_eq!(HashMap::new(), tortuga.config);
+                assert_eq!(Path::new("/etc/systemd/system/hello.service/tortuga.service"), tortuga.unit_path);
+
+                //packaged unit starting a packaged program
+                let rsyslog = systemd_service.iter().find(|v| v.service_name == "rsyslog.service").unwrap();
+                assert_eq!(vec![PathBuf::from("/usr/sbin/rsyslogd")], rsyslog.executables);
+                assert_eq!(Some("rsyslog".to_string()), rsyslog.package);
+                assert!(!rsyslog.highlighted);
+
+                //same program from a unit no package installed
+                let dropped = systemd_service.iter().find(|v| v.service_name == "test.service").unwrap();
+                assert_eq!(None, dropped.package);
+                assert!(dropped.highlighted);
             }
             Err(e) => {
                 panic!("Error getting authorized keys: {:?}", e);
@@ -0,0 +1,444 @@
+pub use crate::prelude::{UserInfo, glob_files, wildcard_match};
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//OpenSSH stops following Include directives after 16 nested files
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshConfigDirective {
+    pub keyword: String,
+    pub arguments: Vec<String>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+//a Match block of sshd_config or a Host/Match block of ssh_config
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshConfigBlock {
+    pub keyword: String,
+    pub criteria: Vec<String>,
+    pub directives: Vec<SshConfigDirective>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshConfig {
+    pub files: Vec<PathBuf>,
+    pub directives: Vec<SshConfigDirective>,
+    pub blocks: Vec<SshConfigBlock>,
+}
+
+//directive of a Match block that applies to the user only on the connections matching the criteria, like
+//Match Address 10.0.0.0/8 or Match LocalPort 2222
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshdConditionalSetting {
+    pub criteria: Vec<String>,
+    pub directive: SshConfigDirective,
+}
+
+//values of the sshd settings that matter to an investigation once Match blocks are applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshdSettings {
+    pub permit_root_login: String,
+    pub password_authentication: bool,
+    pub allow_agent_forwarding: bool,
+    pub authorized_keys_file: Vec<String>,
+    pub authorized_keys_command: Option<String>,
+    pub authorized_keys_command_user: Option<String>,
+    pub force_command: Option<String>,
+    //settings of the Match blocks with criteria of the connection, they cannot be evaluated offline
+    pub conditional_settings: Vec<SshdConditionalSetting>,
+}
+
+impl Default for SshdSettings {
+    //defaults of OpenSSH when the keyword is not present
+    fn default() -> Self {
+        Self {
+            permit_root_login: "prohibit-password".to_string(),
+            password_authentication: true,
+            allow_agent_forwarding: true,
+            authorized_keys_file: vec![
+                ".ssh/authorized_keys".to_string(),
+                ".ssh/authorized_keys2".to_string(),
+            ],
+            authorized_keys_command: None,
+            authorized_keys_command_user: None,
+            force_command: None,
+            conditional_settings: Vec::new(),
+        }
+    }
+}
+
+impl SshConfigDirective {
+    pub fn is(&self, keyword: &str) -> bool {
+        self.keyword.eq_ignore_ascii_case(keyword)
+    }
+
+    pub fn value(&self) -> String {
+        self.arguments.join(" ")
+    }
+}
+
+impl SshConfigBlock {
+    //the block applies to every login of the user
+    pub fn matches_user(&self, user_info: &UserInfo) -> bool {
+        self.connection_criteria(user_info).is_some_and(|v| v.is_empty())
+    }
+
+    //only User and Group criteria can be evaluated offline, None when they reject the user. The other criteria,
+    //Address, Host, LocalAddress, LocalPort and RDomain, are returned as the conditions for the block to apply
+    pub fn connection_criteria(&self, user_info: &UserInfo) -> Option<Vec<String>> {
+        if !self.keyword.eq_ignore_ascii_case("match") {
+            return None;
+        }
+        let mut connection_criteria = Vec::new();
+        let mut criteria = self.criteria.iter();
+        while let Some(criterion) = criteria.next() {
+            let matched = match criterion.to_lowercase().as_str() {
+                "all" => true,
+                "user" => match criteria.next() {
+                    Some(patterns) => match_pattern_list(patterns, &user_info.name),
+                    None => false,
+                },
+                "group" => match criteria.next() {
+                    Some(patterns) => user_info
+                        .groups
+                        .iter()
+                        .any(|group| match_pattern_list(patterns, &group.name)),
+                    None => false,
+                },
+                _ => match criteria.next() {
+                    Some(patterns) => {
+                        connection_criteria.push(format!("{} {}", criterion, patterns));
+                        true
+                    }
+                    None => false,
+                },
+            };
+            if !matched {
+                return None;
+            }
+        }
+        Some(connection_criteria)
+    }
+}
+
+impl SshConfig {
+    //parses /etc/ssh/sshd_config and the files it includes
+    pub fn load_sshd_config(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut config = Self::default();
+        config.process_config_file(vfs, Path::new("/etc/ssh/sshd_config"), Path::new("/etc/ssh"), None, 0);
+        Ok(config)
+    }
+
+    //parses ~/.ssh/config followed by /etc/ssh/ssh_config, the order in which ssh reads them
+    pub fn load_ssh_client_config(
+        vfs: &mut impl VirtualFileSystem,
+        user_home_path: &Path,
+    ) -> ForensicResult<Self> {
+        let mut config = Self::default();
+        let user_ssh_path = user_home_path.join(".ssh");
+        config.process_config_file(vfs, &user_ssh_path.join("config"), &user_ssh_path, None, 0);
+        config.process_config_file(vfs, Path::new("/etc/ssh/ssh_config"), Path::new("/etc/ssh"), None, 0);
+        Ok(config)
+    }
+
+    //the first value obtained for a keyword is the one used by ssh and sshd
+    pub fn get(&self, keyword: &str) -> Option<&SshConfigDirective> {
+        self.directives.iter().find(|v| v.is(keyword))
+    }
+
+    //settings applied by sshd to a login of the user, Match blocks take precedence over global values
+    pub fn sshd_settings_for_user(&self, user_info: &UserInfo) -> SshdSettings {
+        let matching_blocks: Vec<&SshConfigBlock> = self
+            .blocks
+            .iter()
+            .filter(|v| v.matches_user(user_info))
+            .collect();
+        let lookup = |keyword: &str| -> Option<&SshConfigDirective> {
+            matching_blocks
+                .iter()
+                .find_map(|block| block.directives.iter().find(|v| v.is(keyword)))
+                .or_else(|| self.get(keyword))
+        };
+
+        let mut settings = SshdSettings::default();
+        if let Some(v) = lookup("PermitRootLogin") {
+            settings.permit_root_login = v.value().to_lowercase();
+        }
+        if let Some(v) = lookup("PasswordAuthentication") {
+            settings.password_authentication = v.value().eq_ignore_ascii_case("yes");
+        }
+        if let Some(v) = lookup("AllowAgentForwarding") {
+            settings.allow_agent_forwarding = v.value().eq_ignore_ascii_case("yes");
+        }
+        if let Some(v) = lookup("AuthorizedKeysFile") {
+            settings.authorized_keys_file = match v.value().eq_ignore_ascii_case("none") {
+                true => Vec::new(),
+                false => v.arguments.clone(),
+            };
+        }
+        settings.authorized_keys_command = lookup("AuthorizedKeysCommand")
+            .map(|v| v.value())
+            .filter(|v| !v.eq_ignore_ascii_case("none"));
+        settings.authorized_keys_command_user = lookup("AuthorizedKeysCommandUser").map(|v| v.value());
+        settings.force_command = lookup("ForceCommand")
+            .map(|v| v.value())
+            .filter(|v| !v.eq_ignore_ascii_case("none"));
+        for block in &self.blocks {
+            let criteria = match block.connection_criteria(user_info) {
+                Some(v) if !v.is_empty() => v,
+                _ => continue,
+            };
+            for directive in &block.directives {
+                settings.conditional_settings.push(SshdConditionalSetting {
+                    criteria: criteria.clone(),
+                    directive: directive.clone(),
+                });
+            }
+        }
+        settings
+    }
+
+    //reads a config file, the directives go to the current block until a Match or Host starts a new one
+    fn process_config_file(
+        &mut self,
+        vfs: &mut impl VirtualFileSystem,
+        path: &Path,
+        base_path: &Path,
+        mut current_block: Option<usize>,
+        depth: usize,
+    ) {
+        if depth > MAX_INCLUDE_DEPTH {
+            return;
+        }
+        let config_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        self.files.push(path.to_path_buf());
+
+        for (line_number, line) in config_file.lines().enumerate() {
+            let mut arguments = split_config_line(line);
+            if arguments.is_empty() {
+                continue;
+            }
+            let keyword = arguments.remove(0);
+
+            if keyword.eq_ignore_ascii_case("match") || keyword.eq_ignore_ascii_case("host") {
+                self.blocks.push(SshConfigBlock {
+                    keyword,
+                    criteria: arguments,
+                    directives: Vec::new(),
+                    path: path.to_path_buf(),
+                    line_number: line_number + 1,
+                });
+                current_block = Some(self.blocks.len() - 1);
+                continue;
+            }
+
+            if keyword.eq_ignore_ascii_case("include") {
+                for pattern in &arguments {
+                    for included in expand_include(vfs, pattern, base_path) {
+                        self.process_config_file(vfs, &included, base_path, current_block, depth + 1);
+                    }
+                }
+                continue;
+            }
+
+            let directive = SshConfigDirective {
+                keyword,
+                arguments,
+                path: path.to_path_buf(),
+                line_number: line_number + 1,
+            };
+            match current_block {
+                Some(block) => self.blocks[block].directives.push(directive),
+                None => self.directives.push(directive),
+            }
+        }
+    }
+}
+
+//splits "Keyword value", "Keyword=value" and quoted arguments, comments are dropped
+fn split_config_line(line: &str) -> Vec<String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Vec::new();
+    }
+    let (keyword, rest) = match line.find(|c: char| c.is_whitespace() || c == '=') {
+        Some(position) => (&line[..position], &line[position..]),
+        None => (line, ""),
+    };
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+    let mut arguments = vec![keyword.to_string()];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_argument = false;
+    for c in rest.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_argument = true;
+            }
+            '#' if !in_quotes && !has_argument => break,
+            c if c.is_whitespace() && !in_quotes => {
+                if has_argument {
+                    arguments.push(std::mem::take(&mut current));
+                    has_argument = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_argument = true;
+            }
+        }
+    }
+    if has_argument {
+        arguments.push(current);
+    }
+    arguments
+}
+
+//returns the files of an Include pattern sorted like glob(3), wildcards are allowed in the file name
+fn expand_include(vfs: &mut impl VirtualFileSystem, pattern: &str, base_path: &Path) -> Vec<PathBuf> {
+    let pattern = match pattern.strip_prefix("~/") {
+        //~ is the home of the user that owns the ssh directory we are reading
+        Some(v) => base_path.parent().unwrap_or(base_path).join(v),
+        None => base_path.join(pattern),
+    };
+    glob_files(vfs, &pattern)
+}
+
+//comma separated list of patterns, a matching negated pattern rejects the value
+fn match_pattern_list(patterns: &str, value: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split(',') {
+        match pattern.strip_prefix('!') {
+            Some(negated) if wildcard_match(negated, value) => return false,
+            Some(_) => {}
+            None => matched |= wildcard_match(pattern, value),
+        }
+    }
+    matched
+}
+
+#[cfg(test)]
+mod ssh_config_tests {
+    use std::path::{Path, PathBuf};
+
+    use forensic_rs::core::fs::StdVirtualFS;
+
+    use crate::{
+        prelude::{group::Group, UserInfo},
+        ChRootFileSystem,
+    };
+
+    use super::{SshConfig, SshConfigDirective, SshdConditionalSetting, SshdSettings};
+
+    #[test]
+    fn should_resolve_sshd_config_with_includes_and_match_blocks() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+
+        let sshd_config = SshConfig::load_sshd_config(&mut vfs).expect("Should process sshd_config");
+        assert_eq!(3, sshd_config.files.len());
+        assert_eq!(4, sshd_config.blocks.len());
+
+        let user_info = UserInfo {
+            name: "forensicrs".to_string(),
+            id: 1000,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
+            home: PathBuf::from("/home/forensicrs"),
+            shell: "/bin/bash".to_string(),
+            groups: vec![Group {
+                name: "sudo".to_string(),
+                group_id: 27,
+                users: vec!["forensicrs".to_string()],
+                ..Default::default()
+            }],
+        };
+        let settings_test = SshdSettings {
+            permit_root_login: "no".to_string(),
+            password_authentication: true,
+            allow_agent_forwarding: true,
+            authorized_keys_file: vec![
+                ".ssh/authorized_keys".to_string(),
+                "/etc/ssh/authorized_keys/%u".to_string(),
+            ],
+            authorized_keys_command: Some("/usr/local/sbin/fetch-keys %u %f".to_string()),
+            authorized_keys_command_user: Some("nobody".to_string()),
+            force_command: Some("/usr/bin/logger -t ssh-audit && exec $SHELL".to_string()),
+            conditional_settings: vec![SshdConditionalSetting {
+                criteria: vec!["Address 10.0.0.0/8".to_string()],
+                directive: SshConfigDirective {
+                    keyword: "PasswordAuthentication".to_string(),
+                    arguments: vec!["no".to_string()],
+                    path: PathBuf::from("/etc/ssh/sshd_config"),
+                    line_number: 32,
+                },
+            }],
+        };
+        assert_eq!(settings_test, sshd_config.sshd_settings_for_user(&user_info));
+
+        let root_info = UserInfo {
+            name: "root".to_string(),
+            ..Default::default()
+        };
+        let root_settings = sshd_config.sshd_settings_for_user(&root_info);
+        assert_eq!("yes", root_settings.permit_root_login);
+        assert_eq!(None, root_settings.force_command);
+        //the block for root on port 2222 is reported with its criteria
+        let conditional: Vec<(String, String)> = root_settings
+            .conditional_settings
+            .iter()
+            .map(|v| (v.criteria.join(" "), format!("{} {}", v.directive.keyword, v.directive.value())))
+            .collect();
+        assert_eq!(
+            vec![
+                ("Address 10.0.0.0/8".to_string(), "PasswordAuthentication no".to_string()),
+                ("LocalPort 2222".to_string(), "PermitRootLogin yes".to_string()),
+                ("LocalPort 2222".to_string(), "ForceCommand /usr/local/bin/.maint".to_string()),
+            ],
+            conditional
+        );
+    }
+
+    #[test]
+    fn should_process_ssh_client_config() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+
+        let ssh_config = SshConfig::load_ssh_client_config(&mut vfs, Path::new("/home/forensicrs"))
+            .expect("Should process ssh_config");
+
+        assert_eq!(3, ssh_config.blocks.len());
+        assert_eq!(vec!["fileserver".to_string()], ssh_config.blocks[1].criteria);
+        let proxy_jump = ssh_config.blocks[1]
+            .directives
+            .iter()
+            .find(|v| v.is("proxyjump"))
+            .expect("Should exist ProxyJump");
+        assert_eq!("gitlab", proxy_jump.value());
+        assert_eq!(10, proxy_jump.line_number);
+    }
+}
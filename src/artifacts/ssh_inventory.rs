@@ -0,0 +1,490 @@
+pub use crate::prelude::UserInfo;
+pub use crate::ChRootFileSystem;
+use base64::{
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+    Engine,
+};
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use sha2::{Digest, Sha256};
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//files that OpenSSH itself reads or writes inside ~/.ssh
+const KNOWN_SSH_FILES: [&str; 8] = [
+    "authorized_keys",
+    "authorized_keys2",
+    "known_hosts",
+    "known_hosts2",
+    "known_hosts.old",
+    "config",
+    "rc",
+    "environment",
+];
+
+const OPENSSH_KEY_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+//DER encoded object identifiers of the PKCS#8 encryption schemes
+const OID_PBES2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0d];
+const OID_PBKDF2: &[u8] = &[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x05, 0x0c];
+const OID_SCRYPT: &[u8] = &[0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x04, 0x0b];
+const PKCS8_CIPHERS: [(&[u8], &str); 4] = [
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x02], "aes-128-cbc"),
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x16], "aes-192-cbc"),
+    (&[0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x01, 0x2a], "aes-256-cbc"),
+    (&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x03, 0x07], "des-ede3-cbc"),
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PrivateKeyFormat {
+    #[default]
+    Unknown,
+    OpenSsh,
+    Pem,
+    Pkcs8,
+    Putty,
+}
+
+//metadata of a private key, the key material itself is never kept
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshPrivateKey {
+    pub path: PathBuf,
+    pub format: PrivateKeyFormat,
+    pub key_type: String,
+    pub encrypted: bool,
+    pub cipher: String,
+    pub kdf: String,
+    pub kdf_rounds: Option<u32>,
+    pub fingerprint: Option<String>,
+    pub comment: String,
+    pub public_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshPublicKey {
+    pub path: PathBuf,
+    pub key_type: String,
+    pub fingerprint: Option<String>,
+    pub comment: String,
+    pub private_key_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SshInventory {
+    pub private_keys: Vec<SshPrivateKey>,
+    pub public_keys: Vec<SshPublicKey>,
+    pub rc_script: Option<String>,
+    pub environment: Vec<(String, String)>,
+    pub unexpected_files: Vec<PathBuf>,
+}
+
+impl SshInventory {
+    //inventory of the ~/.ssh directory of the user
+    pub fn load_ssh_inventory(
+        user_info: UserInfo,
+        vfs: &mut impl VirtualFileSystem,
+    ) -> ForensicResult<Self> {
+        let mut inventory = Self::default();
+        let ssh_path = user_info.home.join(".ssh");
+        let entries = match vfs.read_dir(&ssh_path) {
+            Ok(v) => v,
+            Err(_e) => return Ok(inventory),
+        };
+
+        let mut file_names: Vec<String> = Vec::new();
+        for entry in entries {
+            match entry {
+                forensic_rs::traits::vfs::VDirEntry::File(file_name) => file_names.push(file_name),
+                //OpenSSH does not create directories or links in ~/.ssh
+                other => inventory.unexpected_files.push(ssh_path.join(other.to_string())),
+            }
+        }
+        file_names.sort();
+
+        for file_name in &file_names {
+            let path = ssh_path.join(file_name);
+            match file_name.as_str() {
+                "rc" => inventory.rc_script = vfs.read_to_string(&path).ok(),
+                "environment" => {
+                    inventory.environment = parse_environment_file(&vfs.read_to_string(&path).unwrap_or_default())
+                }
+                v if KNOWN_SSH_FILES.contains(&v) => {}
+                v if v.ends_with(".pub") => {
+                    let contents = vfs.read_to_string(&path).unwrap_or_default();
+                    match SshPublicKey::parse(&contents) {
+                        Some(mut public_key) => {
+                            public_key.path = path;
+                            inventory.public_keys.push(public_key);
+                        }
+                        None => inventory.unexpected_files.push(path),
+                    }
+                }
+                _ => {
+                    let contents = vfs.read_all(&path).unwrap_or_default();
+                    match SshPrivateKey::parse(&contents) {
+                        Some(mut private_key) => {
+                            private_key.path = path;
+                            inventory.private_keys.push(private_key);
+                        }
+                        None => inventory.unexpected_files.push(path),
+                    }
+                }
+            }
+        }
+
+        inventory.link_key_pairs();
+        Ok(inventory)
+    }
+
+    //pairs id_xxx with id_xxx.pub, the public key fills the fingerprint of formats that do not embed it
+    fn link_key_pairs(&mut self) {
+        for private_key in &mut self.private_keys {
+            let public_key_path = PathBuf::from(format!("{}.pub", private_key.path.to_string_lossy()));
+            if let Some(public_key) = self.public_keys.iter_mut().find(|v| v.path == public_key_path) {
+                public_key.private_key_path = Some(private_key.path.clone());
+                private_key.public_key_path = Some(public_key.path.clone());
+                if private_key.fingerprint.is_none() {
+                    private_key.fingerprint = public_key.fingerprint.clone();
+                }
+                if private_key.key_type.is_empty() {
+                    private_key.key_type = public_key.key_type.clone();
+                }
+                if private_key.comment.is_empty() {
+                    private_key.comment = public_key.comment.clone();
+                }
+            }
+        }
+    }
+}
+
+impl SshPublicKey {
+    pub fn parse(contents: &str) -> Option<Self> {
+        let line = contents.lines().map(|v| v.trim()).find(|v| !v.is_empty() && !v.starts_with('#'))?;
+        let mut columns = line.splitn(3, char::is_whitespace);
+        let key_type = columns.next()?.to_string();
+        let blob = STANDARD.decode(columns.next()?).ok()?;
+        Some(Self {
+            path: PathBuf::new(),
+            key_type,
+            fingerprint: Some(fingerprint(&blob)),
+            comment: columns.next().unwrap_or_default().trim().to_string(),
+            private_key_path: None,
+        })
+    }
+}
+
+impl SshPrivateKey {
+    //recognizes OpenSSH, PEM, PKCS#8 and PuTTY private keys
+    pub fn parse(contents: &[u8]) -> Option<Self> {
+        let contents = std::str::from_utf8(contents).ok()?;
+        let first_line = contents.lines().next()?.trim();
+        if first_line.starts_with("PuTTY-User-Key-File-") {
+            return Some(Self::parse_putty(contents));
+        }
+        let label = first_line
+            .strip_prefix("-----BEGIN ")?
+            .strip_suffix("-----")?
+            .to_string();
+        if !label.ends_with("PRIVATE KEY") {
+            return None;
+        }
+        let (headers, body) = pem_headers_and_body(contents);
+        let der = STANDARD.decode(body).ok()?;
+        let private_key = match label.as_str() {
+            "OPENSSH PRIVATE KEY" => Self::parse_openssh(&der)?,
+            "PRIVATE KEY" => Self {
+                format: PrivateKeyFormat::Pkcs8,
+                cipher: "none".to_string(),
+                kdf: "none".to_string(),
+                ..Default::default()
+            },
+            "ENCRYPTED PRIVATE KEY" => Self::parse_encrypted_pkcs8(&der),
+            _ => Self::parse_pem(&label, &headers),
+        };
+        Some(private_key)
+    }
+
+    //openssh-key-v1 header: cipher, kdf, kdf options, number of keys and the public key blob
+    fn parse_openssh(der: &[u8]) -> Option<Self> {
+        let mut reader = der.strip_prefix(OPENSSH_KEY_MAGIC)?;
+        let cipher = String::from_utf8_lossy(read_ssh_string(&mut reader)?).to_string();
+        let kdf = String::from_utf8_lossy(read_ssh_string(&mut reader)?).to_string();
+        let mut kdf_options = read_ssh_string(&mut reader)?;
+        let kdf_rounds = match kdf.as_str() {
+            "bcrypt" => {
+                read_ssh_string(&mut kdf_options)?;
+                read_u32(&mut kdf_options)
+            }
+            _ => None,
+        };
+        let _keys = read_u32(&mut reader)?;
+        let public_key = read_ssh_string(&mut reader)?;
+        let mut public_key_reader = public_key;
+        let key_type = String::from_utf8_lossy(read_ssh_string(&mut public_key_reader)?).to_string();
+        Some(Self {
+            format: PrivateKeyFormat::OpenSsh,
+            key_type,
+            encrypted: cipher != "none",
+            cipher,
+            kdf,
+            kdf_rounds,
+            fingerprint: Some(fingerprint(public_key)),
+            ..Default::default()
+        })
+    }
+
+    //traditional "RSA/DSA/EC PRIVATE KEY" files, encryption is declared in the Proc-Type and DEK-Info headers
+    fn parse_pem(label: &str, headers: &[(String, String)]) -> Self {
+        let key_type = match label {
+            "RSA PRIVATE KEY" => "ssh-rsa",
+            "DSA PRIVATE KEY" => "ssh-dss",
+            "EC PRIVATE KEY" => "ecdsa",
+            _ => "",
+        };
+        let encrypted = headers
+            .iter()
+            .any(|(name, value)| name == "Proc-Type" && value.contains("ENCRYPTED"));
+        let cipher = headers
+            .iter()
+            .find(|(name, _)| name == "DEK-Info")
+            .and_then(|(_, value)| value.split(',').next())
+            .unwrap_or("none")
+            .to_string();
+        Self {
+            format: PrivateKeyFormat::Pem,
+            key_type: key_type.to_string(),
+            encrypted,
+            cipher,
+            kdf: if encrypted { "md5".to_string() } else { "none".to_string() },
+            ..Default::default()
+        }
+    }
+
+    //EncryptedPrivateKeyInfo: the PBES2 parameters give the key derivation and the cipher
+    fn parse_encrypted_pkcs8(der: &[u8]) -> Self {
+        let mut private_key = Self {
+            format: PrivateKeyFormat::Pkcs8,
+            encrypted: true,
+            ..Default::default()
+        };
+        let algorithm = read_der(der)
+            .and_then(|(_, content, _)| read_der(content))
+            .map(|(_, content, _)| content);
+        let (scheme, parameters) = match algorithm.and_then(|v| read_der(v)) {
+            Some((_, oid, rest)) => (oid, rest),
+            None => return private_key,
+        };
+        if scheme != OID_PBES2 {
+            return private_key;
+        }
+        let parameters = read_der(parameters).map(|(_, content, _)| content).unwrap_or_default();
+        if let Some((_, kdf, encryption)) = read_der(parameters) {
+            if let Some((_, kdf_oid, kdf_parameters)) = read_der(kdf) {
+                private_key.kdf = match kdf_oid {
+                    v if v == OID_PBKDF2 => "pbkdf2".to_string(),
+                    v if v == OID_SCRYPT => "scrypt".to_string(),
+                    _ => "unknown".to_string(),
+                };
+                //PBKDF2-params: salt followed by the iteration count
+                if kdf_oid == OID_PBKDF2 {
+                    private_key.kdf_rounds = read_der(kdf_parameters)
+                        .and_then(|(_, content, _)| read_der(content))
+                        .and_then(|(_, _, rest)| read_der(rest))
+                        .and_then(|(_, iterations, _)| {
+                            iterations.iter().try_fold(0u32, |acc, v| acc.checked_mul(256).map(|acc| acc + *v as u32))
+                        });
+                }
+            }
+            if let Some((_, cipher, _)) = read_der(encryption).and_then(|(_, content, _)| read_der(content)) {
+                private_key.cipher = PKCS8_CIPHERS
+                    .iter()
+                    .find(|(oid, _)| *oid == cipher)
+                    .map(|(_, name)| name.to_string())
+                    .unwrap_or_else(|| "unknown".to_string());
+            }
+        }
+        private_key
+    }
+
+    fn parse_putty(contents: &str) -> Self {
+        let mut private_key = Self {
+            format: PrivateKeyFormat::Putty,
+            kdf: "none".to_string(),
+            ..Default::default()
+        };
+        for line in contents.lines() {
+            let (name, value) = match line.split_once(": ") {
+                Some(v) => v,
+                None => continue,
+            };
+            match name {
+                v if v.starts_with("PuTTY-User-Key-File-") => private_key.key_type = value.trim().to_string(),
+                "Encryption" => {
+                    private_key.cipher = value.trim().to_string();
+                    private_key.encrypted = value.trim() != "none";
+                }
+                "Comment" => private_key.comment = value.trim().to_string(),
+                "Key-Derivation" => private_key.kdf = value.trim().to_lowercase(),
+                "Argon2-Passes" => private_key.kdf_rounds = value.trim().parse::<u32>().ok(),
+                "Public-Lines" => break,
+                _ => {}
+            }
+        }
+        private_key
+    }
+}
+
+//same representation as ssh-keygen -l: SHA256 of the public key blob in unpadded base64
+pub fn fingerprint(public_key_blob: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(public_key_blob)))
+}
+
+fn parse_environment_file(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty() && !v.starts_with('#'))
+        .filter_map(|v| v.split_once('='))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+//returns the "Name: value" headers of a PEM block and its base64 body
+fn pem_headers_and_body(contents: &str) -> (Vec<(String, String)>, String) {
+    let mut headers = Vec::new();
+    let mut body = String::new();
+    for line in contents.lines().map(|v| v.trim()) {
+        if line.starts_with("-----") {
+            continue;
+        }
+        match line.split_once(": ") {
+            Some((name, value)) => headers.push((name.to_string(), value.to_string())),
+            None => body.push_str(line),
+        }
+    }
+    (headers, body)
+}
+
+fn read_u32(reader: &mut &[u8]) -> Option<u32> {
+    let bytes = reader.get(..4)?;
+    *reader = &reader[4..];
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn read_ssh_string<'a>(reader: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let length = read_u32(reader)? as usize;
+    let value = reader.get(..length)?;
+    *reader = &reader[length..];
+    Some(value)
+}
+
+//reads a DER element and returns its tag, its content and the bytes after it
+fn read_der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *data.first()?;
+    let first_length = *data.get(1)? as usize;
+    let (length, header) = if first_length < 0x80 {
+        (first_length, 2)
+    } else {
+        let length_bytes = first_length & 0x7f;
+        if length_bytes > std::mem::size_of::<usize>() {
+            return None;
+        }
+        let length = data
+            .get(2..2 + length_bytes)?
+            .iter()
+            .fold(0usize, |acc, v| (acc << 8) | *v as usize);
+        (length, 2 + length_bytes)
+    };
+    let end = header.checked_add(length)?;
+    let content = data.get(header..end)?;
+    Some((tag, content, &data[end..]))
+}
+
+#[cfg(test)]
+mod ssh_inventory_tests {
+    use std::path::{Path, PathBuf};
+
+    use forensic_rs::core::fs::StdVirtualFS;
+
+    use crate::{prelude::UserInfo, ChRootFileSystem};
+
+    use super::{read_der, PrivateKeyFormat, SshInventory, SshPrivateKey};
+
+    #[test]
+    fn should_process_ssh_inventory() {
+        let user_info = UserInfo {
+            name: "forensicrs".to_string(),
+            id: 1000,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
+            home: PathBuf::from("/home/forensicrs"),
+            shell: "/bin/bash".to_string(),
+            groups: Vec::new(),
+        };
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+
+        let inventory = SshInventory::load_ssh_inventory(user_info, &mut vfs).expect("Should process ~/.ssh");
+
+        let ecdsa_key_test = SshPrivateKey {
+            path: PathBuf::from("/home/forensicrs/.ssh/id_ecdsa_deploy"),
+            format: PrivateKeyFormat::OpenSsh,
+            key_type: "ecdsa-sha2-nistp256".to_string(),
+            encrypted: true,
+            cipher: "aes256-ctr".to_string(),
+            kdf: "bcrypt".to_string(),
+            kdf_rounds: Some(32),
+            fingerprint: Some("SHA256:2WE4IEWNiiY25qM68jmmXzU7zjwwDusjZxrL9+f0feE".to_string()),
+            comment: "deploy".to_string(),
+            public_key_path: Some(PathBuf::from("/home/forensicrs/.ssh/id_ecdsa_deploy.pub")),
+        };
+        assert_eq!(4, inventory.private_keys.len());
+        assert_eq!(ecdsa_key_test, inventory.private_keys[1]);
+
+        let pkcs8_key = &inventory.private_keys[0];
+        assert_eq!(PrivateKeyFormat::Pkcs8, pkcs8_key.format);
+        assert_eq!("aes-256-cbc", pkcs8_key.cipher);
+        assert_eq!(Some(2048), pkcs8_key.kdf_rounds);
+
+        let ed25519_key = &inventory.private_keys[2];
+        assert!(!ed25519_key.encrypted);
+        assert_eq!(
+            Some("SHA256:JnE5JgK1Hw3YKEA3+DmoRbY/DXDcejcCYfoMXjG8W1U".to_string()),
+            ed25519_key.fingerprint
+        );
+
+        let pem_key = &inventory.private_keys[3];
+        assert_eq!(PrivateKeyFormat::Pem, pem_key.format);
+        assert_eq!("AES-128-CBC", pem_key.cipher);
+
+        assert_eq!(2, inventory.public_keys.len());
+        assert!(inventory.rc_script.unwrap_or_default().contains("/tmp/.x/beacon"));
+        assert_eq!(
+            ("LD_PRELOAD".to_string(), "/tmp/.x/libhook.so".to_string()),
+            inventory.environment[1]
+        );
+        assert_eq!(
+            vec![PathBuf::from("/home/forensicrs/.ssh/notes.txt")],
+            inventory.unexpected_files
+        );
+    }
+
+    #[test]
+    fn should_reject_der_lengths_past_the_end() {
+        assert_eq!(Some((0x02, &[0x05][..], &[][..])), read_der(&[0x02, 0x01, 0x05]));
+        //long form lengths that overflow or point past the data
+        let mut overflow = vec![0x30, 0x88];
+        overflow.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_eq!(None, read_der(&overflow));
+        assert_eq!(None, read_der(&[0x30, 0x89, 1, 0, 0, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(None, read_der(&[0x30, 0x82, 0x01, 0x00, 0x00]));
+    }
+}
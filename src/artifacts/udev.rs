@@ -0,0 +1,237 @@
+pub use crate::ChRootFileSystem;
+pub use forensic_rs::{
+    core::fs::StdVirtualFS, prelude::ForensicResult, traits::vfs::VirtualFileSystem,
+};
+use lazy_static::lazy_static;
+use regex::Regex;
+pub use std::{
+    fs,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+lazy_static! {
+    static ref UDEV_KEY_REGEX: Regex =
+        Regex::new(r#"([A-Za-z_]+)(?:\{([^}]*)\})?\s*(==|!=|\+=|-=|:=|=)\s*"((?:[^"\\]|\\.)*)""#).unwrap();
+}
+
+const ETC_RULES_DIR: &str = "/etc/udev/rules.d";
+//a rules file of /etc overrides the files with the same name of the other directories
+const RULES_DIRS: [&str; 4] = [ETC_RULES_DIR, "/run/udev/rules.d", "/lib/udev/rules.d", "/usr/lib/udev/rules.d"];
+//directories any user or service can write to
+const WRITABLE_DIRS: [&str; 7] = ["/tmp/", "/var/tmp/", "/dev/shm/", "/home/", "/root/", "/run/user/", "/var/www/"];
+
+//KEY{attribute}operator"value"
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UdevKey {
+    pub key: String,
+    pub attribute: Option<String>,
+    pub operator: String,
+    pub value: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UdevRule {
+    pub matches: Vec<UdevKey>,
+    pub assignments: Vec<UdevKey>,
+    pub path: PathBuf,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum UdevCommandKind {
+    #[default]
+    Run,
+    Program,
+    ImportProgram,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UdevCommand {
+    pub kind: UdevCommandKind,
+    pub command: String,
+    pub path: PathBuf,
+    pub line_number: usize,
+    //the rules file is in /etc and no package provides one with the same name
+    pub etc_only: bool,
+    pub references_writable_path: bool,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct UdevRules {
+    pub files: Vec<PathBuf>,
+    pub rules: Vec<UdevRule>,
+    pub commands: Vec<UdevCommand>,
+}
+
+impl UdevRules {
+    pub fn load_udev_rules(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let mut udev_rules = Self::default();
+        for directory in RULES_DIRS {
+            let directory = Path::new(directory);
+            let mut file_names: Vec<String> = match vfs.read_dir(directory) {
+                Ok(entries) => entries
+                    .into_iter()
+                    .filter(|v| !matches!(v, forensic_rs::traits::vfs::VDirEntry::Directory(_)))
+                    .map(|v| v.to_string())
+                    .filter(|v| v.ends_with(".rules"))
+                    .collect(),
+                Err(_e) => continue,
+            };
+            file_names.sort();
+            for file_name in file_names {
+                udev_rules.process_rules_file(vfs, &directory.join(file_name));
+            }
+        }
+
+        let other_file_names: Vec<String> = udev_rules
+            .files
+            .iter()
+            .filter(|v| !v.starts_with(ETC_RULES_DIR))
+            .filter_map(|v| v.file_name())
+            .map(|v| v.to_string_lossy().to_string())
+            .collect();
+        udev_rules.commands = udev_rules.rules.iter().flat_map(|rule| {
+            let etc_only = rule.path.starts_with(ETC_RULES_DIR)
+                && !other_file_names.iter().any(|v| rule.path.ends_with(v));
+            rule_commands(rule, etc_only)
+        }).collect();
+        Ok(udev_rules)
+    }
+
+    //commands of /etc only rules that run something from a writable directory
+    pub fn suspicious_commands(&self) -> Vec<&UdevCommand> {
+        self.commands
+            .iter()
+            .filter(|v| v.etc_only && v.references_writable_path)
+            .collect()
+    }
+
+    fn process_rules_file(&mut self, vfs: &mut impl VirtualFileSystem, path: &Path) {
+        let rules_file = match vfs.read_to_string(path) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        self.files.push(path.to_path_buf());
+
+        let mut line = String::new();
+        let mut first_line_number = 0;
+        for (line_number, physical_line) in rules_file.lines().enumerate() {
+            if line.is_empty() {
+                first_line_number = line_number + 1;
+            }
+            if let Some(v) = physical_line.strip_suffix('\\') {
+                line.push_str(v);
+                continue;
+            }
+            line.push_str(physical_line);
+            let logical_line = std::mem::take(&mut line);
+            let logical_line = logical_line.trim();
+            if logical_line.is_empty() || logical_line.starts_with('#') {
+                continue;
+            }
+
+            let mut rule = UdevRule {
+                path: path.to_path_buf(),
+                line_number: first_line_number,
+                ..Default::default()
+            };
+            for udev_key in parse_rule_keys(logical_line) {
+                match udev_key.operator.as_str() {
+                    "==" | "!=" => rule.matches.push(udev_key),
+                    _ => rule.assignments.push(udev_key),
+                }
+            }
+            self.rules.push(rule);
+        }
+    }
+}
+
+//RUN and RUN{program}, PROGRAM and IMPORT{program}, RUN{builtin} runs code of udev itself
+fn rule_commands(rule: &UdevRule, etc_only: bool) -> Vec<UdevCommand> {
+    rule.matches
+        .iter()
+        .chain(rule.assignments.iter())
+        .filter_map(|udev_key| {
+            let kind = match (udev_key.key.as_str(), udev_key.attribute.as_deref()) {
+                ("RUN", None) | ("RUN", Some("program")) => UdevCommandKind::Run,
+                ("PROGRAM", _) => UdevCommandKind::Program,
+                ("IMPORT", Some("program")) => UdevCommandKind::ImportProgram,
+                _ => return None,
+            };
+            Some(UdevCommand {
+                kind,
+                command: udev_key.value.clone(),
+                path: rule.path.clone(),
+                line_number: rule.line_number,
+                etc_only,
+                references_writable_path: udev_key
+                    .value
+                    .split_whitespace()
+                    .any(|v| WRITABLE_DIRS.iter().any(|dir| v.trim_matches('\'').starts_with(dir))),
+            })
+        })
+        .collect()
+}
+
+//comma separated keys, udev requires the values to be double quoted
+fn parse_rule_keys(line: &str) -> Vec<UdevKey> {
+    UDEV_KEY_REGEX
+        .captures_iter(line)
+        .map(|captures| UdevKey {
+            key: captures[1].to_string(),
+            attribute: captures.get(2).map(|v| v.as_str().to_string()),
+            operator: captures[3].to_string(),
+            value: captures[4].replace("\\\"", "\""),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod udev_tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_udev_rules_and_commands() {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let virtual_file_system = &Path::new(&base_path).join("artifacts");
+
+        let mut _std_vfs = StdVirtualFS::new();
+        let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+        let udev_rules = UdevRules::load_udev_rules(&mut vfs).expect("Should load udev rules");
+
+        let usb_rule = udev_rules
+            .rules
+            .iter()
+            .find(|v| v.path.ends_with("99-usb-sync.rules"))
+            .unwrap();
+        let match_test = UdevKey {
+            key: "ATTRS".to_string(),
+            attribute: Some("idVendor".to_string()),
+            operator: "==".to_string(),
+            value: "0781".to_string(),
+        };
+        assert_eq!(vec!["ACTION", "SUBSYSTEM", "ATTRS"], usb_rule.matches.iter().map(|v| v.key.as_str()).collect::<Vec<&str>>());
+        assert_eq!(match_test, usb_rule.matches[2]);
+        assert_eq!(2, usb_rule.line_number);
+
+        let commands: Vec<(&UdevCommandKind, &str, bool)> = udev_rules
+            .commands
+            .iter()
+            .map(|v| (&v.kind, v.command.as_str(), v.etc_only))
+            .collect();
+        assert_eq!(
+            vec![
+                (&UdevCommandKind::ImportProgram, "/usr/lib/udev/hwdb-helper %k", false),
+                (&UdevCommandKind::Run, "/bin/sh -c '/tmp/.u/sync.sh $kernel, now'", true),
+                (&UdevCommandKind::Program, "cdrom_id --lock-media $devnode", false),
+                (&UdevCommandKind::Run, "/lib/udev/cdrom-ready %k", false),
+            ],
+            commands
+        );
+
+        let suspicious = udev_rules.suspicious_commands();
+        assert_eq!(1, suspicious.len());
+        assert_eq!(Path::new("/etc/udev/rules.d/99-usb-sync.rules"), suspicious[0].path);
+    }
+}
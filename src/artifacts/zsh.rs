@@ -69,7 +69,7 @@ impl ZshHistory {
     ) -> ForensicResult<Self> {
         let mut zsh_history = Self::default();
 
-        let user_home = user_info.home.as_path().join(".zsh_history");
+        let user_home = user_info.home.as_path();
 
         zsh_history.read_history_timestamps(user_home, fs);
 
@@ -165,6 +165,9 @@ mod zsh_tests {
         let user_info = UserInfo {
             name: "forensicrs".to_string(),
             id: 1,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
             home: PathBuf::from("/home/forensicrs"),
             shell: "/bin/zsh".to_string(),
             groups: Vec::new(),
@@ -223,6 +226,9 @@ mod zsh_tests {
         let user_info = UserInfo {
             name: "forensicrs".to_string(),
             id: 1,
+            gid: 1000,
+            gecos: String::new(),
+            password: Default::default(),
             home: PathBuf::from("/home/forensicrs"),
             shell: "/bin/zsh".to_string(),
             groups: Vec::new(),
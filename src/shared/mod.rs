@@ -6,15 +6,22 @@ use lazy_static::lazy_static;
 use regex::{Captures, Regex};
 use std::{
     collections::{BTreeSet, HashMap},
-    io::BufRead,
-    path::PathBuf,
+    io::{Read, Write},
+    path::{Component, Path, PathBuf},
 };
 
 use crate::prelude::{
-    group::{ Group, SystemGroups}, bash::BashHistory, zsh::{ZshRcConfig, ZshHistory}, authorized_keys::AuthorizedKey, known_hosts::KnownHost, crontab::{CrontabTask, CrontabSchedule}, services::{InitdService, SystemdService},
+    group::{ Group, SystemGroups}, bash::BashHistory, desktop::DesktopPersistence, package_history::PackageHistory, containers::ContainerInventory, credentials::CredentialInventory, packages::PackageInventory, zsh::{ZshRcConfig, ZshHistory}, authorized_keys::AuthorizedKey, known_hosts::{KnownHost, HostCandidate}, crontab::{CrontabTask, CrontabSchedule}, services::{InitdService, SystemdService}, ssh_config::{SshConfig, SshdSettings}, ssh_inventory::SshInventory, sudoers::{EffectiveSudoRule, SudoersPolicy},
 };
 pub use crate::{BashRcConfig, ChRootFileSystem};
 
+pub mod toml;
+
+//symbolic links followed while resolving one path, the same limit as Linux
+pub(crate) const MAX_SYMLINK_FOLLOWS: usize = 40;
+//rotated logs are decompressed in memory, a real log this size is already rare
+pub const MAX_DECOMPRESSED_SIZE: u64 = 64 << 20;
+
 lazy_static! {
     pub static ref VARIABLE_REGEX: Regex = Regex::new(
         r#"^\s*([A-Za-z_][A-Za-z0-9_]*)\s*=(?:(?:'(.*)')|(?:"(.*)")|([^#\n]*))\s*(?:#.*)?"#
@@ -28,18 +35,55 @@ lazy_static! {
             .unwrap();
 }
 
+//what the password field of /etc/passwd holds
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum PasswordPlaceholder {
+    //"x", the hash lives in /etc/shadow
+    #[default]
+    Shadowed,
+    //"*" or "!", the account cannot log in with a password
+    Locked,
+    //no password is required
+    Empty,
+    //legacy hash stored in /etc/passwd itself
+    Hash,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct UserInfo {
     pub name: String,
     pub id: u32,
+    pub gid: u32,
+    pub gecos: String,
+    pub password: PasswordPlaceholder,
     pub home: PathBuf,
     pub shell: String,
     pub groups: Vec<Group>,
 }
 
+//+/- entries of the NIS compat mode, the name can be empty (every account) or a @netgroup
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NisCompatEntry {
+    pub include: bool,
+    pub name: String,
+    pub line_number: usize,
+    pub line: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PasswdLineError {
+    pub line_number: usize,
+    pub line: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct SystemInfo {
     pub users: Vec<UserInfo>,
+    pub nis_entries: Vec<NisCompatEntry>,
+    pub errors: Vec<PasswdLineError>,
+    //nsswitch.conf sources other than local files, accounts from them are not in /etc/passwd
+    pub remote_account_sources: Vec<String>,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -51,123 +95,230 @@ pub struct UserArtifact {
     pub zsh_history: ZshHistory,
     pub authorized_keys: Vec<AuthorizedKey>,
     pub known_hosts: Vec<KnownHost>,
+    pub sshd_settings: SshdSettings,
+    pub ssh_client_config: SshConfig,
+    pub ssh_inventory: SshInventory,
+    pub sudo_rules: Vec<EffectiveSudoRule>,
+    pub desktop_persistence: DesktopPersistence,
+    pub credentials: CredentialInventory,
     pub programmed_tasks: Vec<CrontabTask>,
     pub groups: Vec<Group>,
     pub init_services: Vec<InitdService>,
     pub systemd_services: Vec<SystemdService>
 }
 
+//artifacts of every user plus the ones that apply to the whole system
+#[derive(Debug, Default, Clone)]
+pub struct SystemArtifact {
+    pub user_artifacts: Vec<UserArtifact>,
+    pub desktop_persistence: DesktopPersistence,
+    pub package_history: PackageHistory,
+    pub containers: ContainerInventory,
+}
+
 impl UserArtifact {
-    pub fn get_user_artifacts(username: String, vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+    //the package inventory is loaded once for all the users, it tags services and crontab tasks
+    pub fn get_user_artifacts(
+        username: String,
+        vfs: &mut impl VirtualFileSystem,
+        package_inventory: &PackageInventory,
+    ) -> ForensicResult<Self> {
         let userinfo = UserInfo::get_user_info(username, vfs)?;
         let mut crontab_schedule = CrontabSchedule::default();
         let system_groups = SystemGroups::process_group_file(vfs)?;
+        let bash_history = BashHistory::load_bash_history(userinfo.clone(), vfs)?;
+        let zsh_history = ZshHistory::load_zsh_history(userinfo.clone(), vfs)?;
+
+        //hashed known_hosts entries are tested against every hostname we can find
+        let mut known_hosts = KnownHost::get_known_hosts(vfs, userinfo.home.clone())?;
+        known_hosts.extend(KnownHost::get_system_known_hosts(vfs)?);
+        let mut host_candidates = HostCandidate::from_etc_hosts(vfs);
+        host_candidates.extend(HostCandidate::from_shell_history(&bash_history.commands));
+        host_candidates.extend(HostCandidate::from_shell_history(&zsh_history.commands));
+        host_candidates.extend(HostCandidate::from_auth_logs(vfs));
+        KnownHost::resolve_hashed_hosts(&mut known_hosts, &host_candidates);
+
+        //authorized_keys are read from the files sshd would use for this user
+        let sshd_settings = SshConfig::load_sshd_config(vfs)?.sshd_settings_for_user(&userinfo);
+        let authorized_keys = AuthorizedKey::get_authorized_keys_for_user(vfs, &userinfo,
+            &sshd_settings.authorized_keys_file)?;
 
         Ok(UserArtifact {
             user_info: userinfo.clone(),
             bash_config: BashRcConfig::load_bash_config(userinfo.clone(), vfs)?,
-            bash_history: BashHistory::load_bash_history(userinfo.clone(), vfs)?,
+            bash_history,
             zsh_config: ZshRcConfig::load_zsh_config(userinfo.clone(), vfs)?,
-            zsh_history: ZshHistory::load_zsh_history(userinfo.clone(), vfs)?,
-            authorized_keys: AuthorizedKey::get_authorized_keys(vfs, 
-                userinfo.home.clone())?,
-            known_hosts: KnownHost::get_known_hosts(vfs, userinfo.home.clone())?,
+            zsh_history,
+            authorized_keys,
+            known_hosts,
+            sshd_settings,
+            ssh_client_config: SshConfig::load_ssh_client_config(vfs, &userinfo.home)?,
+            ssh_inventory: SshInventory::load_ssh_inventory(userinfo.clone(), vfs)?,
+            sudo_rules: SudoersPolicy::load_sudoers(vfs)?.rules_for_user(&userinfo),
+            desktop_persistence: DesktopPersistence::load_user_desktop_persistence(userinfo.clone(), vfs)?,
+            credentials: CredentialInventory::load_credentials(userinfo.clone(), vfs)?,
             programmed_tasks: CrontabSchedule::process_crontab_files(&mut crontab_schedule, 
-                vfs, userinfo.name.clone())?,
-            groups: system_groups.get_groups_for_user(&userinfo.name.clone())?,
-            init_services: InitdService::process_init_services_files(vfs)?,
-            systemd_services: SystemdService::process_services_files(vfs)?
+                vfs, userinfo.name.clone(), package_inventory)?,
+            groups: system_groups.get_groups_for_user(&userinfo.name.clone(), userinfo.gid)?,
+            init_services: InitdService::process_init_services_files(vfs, package_inventory)?,
+            systemd_services: SystemdService::process_services_files(vfs, package_inventory)?
         })
 
     }
 
     pub fn get_system_artifacts(users: SystemInfo, vfs: &mut impl VirtualFileSystem) -> ForensicResult<Vec<Self>> {
         let mut system_artifacts: Vec<Self> = Vec::new();
+        //without a readable package database the ownership of every file is unknown
+        let package_inventory = PackageInventory::load_packages(vfs).unwrap_or_default();
         for user in users.users {
             let username = user.name;
-            let user_artifact = Self::get_user_artifacts(username, vfs)?;
+            let user_artifact = Self::get_user_artifacts(username, vfs, &package_inventory)?;
             system_artifacts.push(user_artifact);
         }
         Ok(system_artifacts)
     }
 }
 
+impl SystemArtifact {
+    pub fn get_system_artifact(users: SystemInfo, vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
+        let package_history = PackageHistory::load_package_history(&users, vfs)?;
+        Ok(SystemArtifact {
+            user_artifacts: UserArtifact::get_system_artifacts(users, vfs)?,
+            desktop_persistence: DesktopPersistence::load_system_desktop_persistence(vfs)?,
+            package_history,
+            containers: ContainerInventory::load_containers(vfs)?,
+        })
+    }
+}
+
 impl UserInfo {
     pub fn get_user_info(username: String, vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
         let passwd_file = vfs.read_to_string(std::path::PathBuf::from("/etc/passwd").as_path())?;
         let mut user_info = UserInfo::default();
     
         for line in passwd_file.lines() {
-            let columns: Vec<&str> = line.split(':').collect();
-    
-            if columns.len() < 7 {
+            if line.split(':').next() != Some(username.as_str()) {
                 continue;
             }
-    
-            if columns[0] == username {
-                let id = columns[2].parse::<u32>().map_err(|_| ForensicError::BadFormat)?;
-                let home = PathBuf::from(columns[5]);
-                let shell = columns[6].to_string();
-                let groups = SystemInfo::get_user_groups(vfs, &username)?;
-                user_info = UserInfo {
-                    name: username.clone(),
-                    id,
-                    home,
-                    shell,
-                    groups,
-                };
-            }
+            user_info = Self::parse_passwd_line(line).map_err(|_| ForensicError::BadFormat)?;
+            user_info.groups = SystemInfo::get_user_groups(vfs, &username, user_info.gid)?;
         }
         Ok(user_info)
     }
+
+    //parses name:password:uid:gid:gecos:home:shell, the groups are not filled
+    pub fn parse_passwd_line(line: &str) -> Result<Self, String> {
+        let columns: Vec<&str> = line.split(':').collect();
+        if columns.len() != 7 {
+            return Err(format!("expected 7 fields, found {}", columns.len()));
+        }
+        if columns[0].is_empty() {
+            return Err("empty user name".to_string());
+        }
+        let id = columns[2]
+            .parse::<u32>()
+            .map_err(|_| format!("invalid UID {}", columns[2]))?;
+        let gid = columns[3]
+            .parse::<u32>()
+            .map_err(|_| format!("invalid GID {}", columns[3]))?;
+        let password = match columns[1] {
+            "x" => PasswordPlaceholder::Shadowed,
+            "" => PasswordPlaceholder::Empty,
+            v if v.starts_with('*') || v.starts_with('!') => PasswordPlaceholder::Locked,
+            _ => PasswordPlaceholder::Hash,
+        };
+        Ok(UserInfo {
+            name: columns[0].to_string(),
+            id,
+            gid,
+            gecos: columns[4].to_string(),
+            password,
+            home: PathBuf::from(columns[5]),
+            shell: columns[6].to_string(),
+            groups: Vec::new(),
+        })
+    }
 }
 
 
 impl SystemInfo {
+    //malformed lines are kept in errors instead of failing the whole load
     pub fn load(vfs: &mut impl VirtualFileSystem) -> ForensicResult<Self> {
         // Load user info from /etc/passwd ...
         let passwd = vfs.read_to_string(std::path::PathBuf::from("/etc/passwd").as_path())?;
-        let reader_passwd = std::io::BufReader::new(passwd.as_bytes());
-        let mut users = Vec::with_capacity(64);
+        let system_groups = SystemGroups::process_group_file(vfs).unwrap_or_default();
+        let mut system_info = Self {
+            users: Vec::with_capacity(64),
+            remote_account_sources: Self::get_remote_account_sources(vfs),
+            ..Default::default()
+        };
 
-        for passwd_line in reader_passwd.lines() {
-            let passwd_line = passwd_line?;
-            if passwd_line.is_empty() {
+        for (line_number, passwd_line) in passwd.lines().enumerate() {
+            let line_number = line_number + 1;
+            if passwd_line.trim().is_empty() || passwd_line.starts_with('#') {
                 continue;
             }
-            let passwd_columns: Vec<&str> = passwd_line.split(":").collect();
-            let new_user = UserInfo {
-                name: passwd_columns
-                    .get(0)
-                    .ok_or_else(|| ForensicError::BadFormat)?
-                    .to_string(),
-                id: passwd_columns
-                    .get(2)
-                    .ok_or_else(|| ForensicError::BadFormat)?
-                    .parse::<u32>()
-                    .map_err(|_| ForensicError::BadFormat)?,
-                home: PathBuf::from(
-                    passwd_columns
-                        .get(5)
-                        .ok_or_else(|| ForensicError::BadFormat)?
-                        .to_string(),
-                ),
-                shell: passwd_columns[6].to_owned(),
-                groups: Self::get_user_groups(vfs, passwd_columns[1])?,
-            };
-            users.push(new_user)
+            if passwd_line.starts_with('+') || passwd_line.starts_with('-') {
+                system_info.nis_entries.push(NisCompatEntry {
+                    include: passwd_line.starts_with('+'),
+                    name: passwd_line[1..].split(':').next().unwrap_or_default().to_string(),
+                    line_number,
+                    line: passwd_line.to_string(),
+                });
+                continue;
+            }
+            match UserInfo::parse_passwd_line(passwd_line) {
+                Ok(mut new_user) => {
+                    new_user.groups = system_groups.get_groups_for_user(&new_user.name, new_user.gid)?;
+                    system_info.users.push(new_user);
+                }
+                Err(reason) => system_info.errors.push(PasswdLineError {
+                    line_number,
+                    line: passwd_line.to_string(),
+                    reason,
+                }),
+            }
         }
-        //TODO: Rellenar con la info de usuarios sacada de /etc/passwd
-        Ok(Self { users })
+        Ok(system_info)
     }
 
     pub fn get_user_groups(
         vfs: &mut impl VirtualFileSystem,
         username: &str,
+        primary_gid: u32,
     ) -> ForensicResult<Vec<Group>> {
         // Load user info from /etc/groups ...
         let system_groups = SystemGroups::process_group_file(vfs)?;
-        Ok(system_groups.get_groups_for_user(username)?)
+        Ok(system_groups.get_groups_for_user(username, primary_gid)?)
+    }
+
+    //sources of the passwd, group and shadow databases in /etc/nsswitch.conf that are not local files
+    pub fn get_remote_account_sources(vfs: &mut impl VirtualFileSystem) -> Vec<String> {
+        let mut sources: Vec<String> = Vec::new();
+        let nsswitch = match vfs.read_to_string(std::path::Path::new("/etc/nsswitch.conf")) {
+            Ok(v) => v,
+            Err(_e) => return sources,
+        };
+        for line in nsswitch.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let (database, database_sources) = match line.split_once(':') {
+                Some(v) => v,
+                None => continue,
+            };
+            if !["passwd", "group", "shadow"].contains(&database.trim()) {
+                continue;
+            }
+            for source in database_sources.split_whitespace() {
+                //[NOTFOUND=return] like actions are not sources
+                if source.starts_with('[') || ["files", "compat", "systemd", "cache", "db"].contains(&source) {
+                    continue;
+                }
+                if !sources.contains(&source.to_string()) {
+                    sources.push(source.to_string());
+                }
+            }
+        }
+        sources
     }
 }
 
@@ -207,6 +358,295 @@ pub fn keys_and_values_from_regex(captures: Captures) -> (&str, &str) {
     (key, value)
 }
 
+//glob matching with the * and ? wildcards used by ssh patterns and file names
+pub fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while v < value.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == value[v]) {
+            p += 1;
+            v += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, v));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            v = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|v| *v == '*')
+}
+
+//timestamps and size reported by the VFS, VMetadata cannot be cloned or compared
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FileMetadata {
+    pub created: Option<usize>,
+    pub accessed: Option<usize>,
+    pub modified: Option<usize>,
+    pub size: u64,
+}
+
+impl FileMetadata {
+    pub fn from_vfs(vfs: &mut impl VirtualFileSystem, path: &Path) -> Option<Self> {
+        let metadata = vfs.metadata(path).ok()?;
+        Some(Self {
+            created: metadata.created,
+            accessed: metadata.accessed,
+            modified: metadata.modified,
+            size: metadata.size,
+        })
+    }
+}
+
+//sqlite can only open files, the database is copied from the VFS to a temporary file
+pub fn with_sqlite_database<T>(
+    vfs: &mut impl VirtualFileSystem,
+    path: &Path,
+    query: impl FnOnce(&rusqlite::Connection) -> rusqlite::Result<T>,
+) -> ForensicResult<T> {
+    let contents = vfs.read_all(path)?;
+    let temporary_path = std::env::temp_dir().join(format!(
+        "tfe-{}-{}.sqlite",
+        std::process::id(),
+        TEMPORARY_DATABASES.fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+    ));
+    std::fs::write(&temporary_path, contents)?;
+    let result = rusqlite::Connection::open_with_flags(&temporary_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .and_then(|connection| query(&connection));
+    let _ = std::fs::remove_file(&temporary_path);
+    result.map_err(|e| ForensicError::Other(e.to_string()))
+}
+
+static TEMPORARY_DATABASES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+//files and links of the directory sorted by name, going down depth levels of subdirectories
+pub fn list_files(vfs: &mut impl VirtualFileSystem, directory: &Path, depth: usize) -> Vec<PathBuf> {
+    let mut entries = match vfs.read_dir(directory) {
+        Ok(v) => v,
+        Err(_e) => return Vec::new(),
+    };
+    entries.sort_by_key(|v| v.to_string());
+    let mut files = Vec::new();
+    for entry in entries {
+        match entry {
+            forensic_rs::traits::vfs::VDirEntry::Directory(name) if depth > 0 => {
+                files.extend(list_files(vfs, &directory.join(name), depth - 1));
+            }
+            forensic_rs::traits::vfs::VDirEntry::Directory(_) => {}
+            other => files.push(directory.join(other.to_string())),
+        }
+    }
+    files
+}
+
+//files of the directory matching the * and ? wildcards of the last path component, sorted by name
+pub fn glob_files(vfs: &mut impl VirtualFileSystem, pattern: &Path) -> Vec<PathBuf> {
+    let file_pattern = match pattern.file_name() {
+        Some(v) => v.to_string_lossy().to_string(),
+        None => return Vec::new(),
+    };
+    if !file_pattern.contains(['*', '?']) {
+        return vec![pattern.to_path_buf()];
+    }
+    let directory = pattern.parent().unwrap_or(Path::new("/"));
+    list_files(vfs, directory, 0)
+        .into_iter()
+        .filter(|v| v.file_name().is_some_and(|name| wildcard_match(&file_pattern, &name.to_string_lossy())))
+        .collect()
+}
+
+//members are stored as "./etc/passwd", "etc/passwd" or "/etc/passwd"
+pub(crate) fn absolute_path(path: &Path) -> PathBuf {
+    let mut absolute = PathBuf::from("/");
+    for component in path_components(path) {
+        if component == ".." {
+            absolute.pop();
+        } else {
+            absolute.push(component);
+        }
+    }
+    absolute
+}
+
+pub(crate) fn path_components(path: &Path) -> Vec<String> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(v) => Some(v.to_string_lossy().to_string()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+//compression of a file detected from its magic bytes, logrotate can be configured with any of them
+#[derive(Debug, Default, Clone, PartialEq)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Xz,
+    Bzip2,
+    Zstd,
+}
+
+impl Compression {
+    pub fn detect(contents: &[u8]) -> Self {
+        if contents.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if contents.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else if contents.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if contents.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+//contents of the file, decompressed when it starts with a known magic whatever its extension
+pub fn read_decompressed(vfs: &mut impl VirtualFileSystem, path: &Path) -> ForensicResult<Vec<u8>> {
+    decompress(vfs.read_all(path)?)
+}
+
+pub fn decompress(contents: Vec<u8>) -> ForensicResult<Vec<u8>> {
+    decompress_with_limit(contents, MAX_DECOMPRESSED_SIZE)
+}
+
+//a few KB of input can expand to gigabytes, more than limit bytes of output is taken as a decompression bomb
+pub fn decompress_with_limit(contents: Vec<u8>, limit: u64) -> ForensicResult<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let result = match Compression::detect(&contents) {
+        Compression::None => return Ok(contents),
+        Compression::Gzip => flate2::read::MultiGzDecoder::new(contents.as_slice())
+            .take(limit + 1)
+            .read_to_end(&mut decompressed)
+            .map(|_| ()),
+        Compression::Bzip2 => bzip2::read::MultiBzDecoder::new(contents.as_slice())
+            .take(limit + 1)
+            .read_to_end(&mut decompressed)
+            .map(|_| ()),
+        //lzma-rs only writes its output, the writer stops it at the limit
+        Compression::Xz => {
+            let mut writer = LimitedWriter { buffer: &mut decompressed, limit };
+            lzma_rs::xz_decompress(&mut contents.as_slice(), &mut writer)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+        }
+        Compression::Zstd => ruzstd::StreamingDecoder::new(contents.as_slice())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+            .and_then(|v| v.take(limit + 1).read_to_end(&mut decompressed))
+            .map(|_| ()),
+    };
+    match result {
+        Ok(_) if decompressed.len() as u64 <= limit => Ok(decompressed),
+        _ => Err(ForensicError::BadFormat),
+    }
+}
+
+struct LimitedWriter<'a> {
+    buffer: &'a mut Vec<u8>,
+    limit: u64,
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if (self.buffer.len() + buf.len()) as u64 > self.limit {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "decompressed data over the limit"));
+        }
+        self.buffer.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+//line of a rotated log family with the file it was read from
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RotatedLine {
+    pub path: PathBuf,
+    pub line_number: usize,
+    pub line: String,
+}
+
+//base, base.1, base.2.gz and base-YYYYMMDD[.gz] files, oldest first so the lines keep the order they were written
+pub fn rotated_family(vfs: &mut impl VirtualFileSystem, base: &Path) -> Vec<PathBuf> {
+    let base_name = match base.file_name() {
+        Some(v) => v.to_string_lossy().to_string(),
+        None => return Vec::new(),
+    };
+    let directory = base.parent().unwrap_or(Path::new("/")).to_path_buf();
+    let mut family: Vec<(PathBuf, RotationOrder)> = match vfs.read_dir(&directory) {
+        Ok(entries) => entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                forensic_rs::traits::vfs::VDirEntry::File(name) => Some(name),
+                _ => None,
+            })
+            .filter_map(|name| {
+                let rotation = RotationOrder::from_suffix(name.strip_prefix(&base_name)?)?;
+                Some((directory.join(name), rotation))
+            })
+            .collect(),
+        Err(_e) => Vec::new(),
+    };
+    family.sort_by(|a, b| a.1.cmp(&b.1));
+    family.into_iter().map(|(path, _)| path).collect()
+}
+
+//every line of the rotated family, unreadable or corrupted rotations are skipped
+pub fn read_rotated_lines(vfs: &mut impl VirtualFileSystem, base: &Path) -> Vec<RotatedLine> {
+    let mut lines = Vec::new();
+    for path in rotated_family(vfs, base) {
+        let contents = match read_decompressed(vfs, &path) {
+            Ok(v) => v,
+            Err(_e) => continue,
+        };
+        for (line_number, line) in String::from_utf8_lossy(&contents).lines().enumerate() {
+            lines.push(RotatedLine {
+                path: path.clone(),
+                line_number: line_number + 1,
+                line: line.to_string(),
+            });
+        }
+    }
+    lines
+}
+
+//the variant order is the age order: dated rotations, numbered ones from the highest and the current file
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum RotationOrder {
+    Dated(String),
+    Numbered(std::cmp::Reverse<u32>),
+    Current,
+}
+
+impl RotationOrder {
+    fn from_suffix(suffix: &str) -> Option<Self> {
+        let suffix = [".gz", ".xz", ".bz2", ".zst"]
+            .iter()
+            .find_map(|v| suffix.strip_suffix(v))
+            .unwrap_or(suffix);
+        if suffix.is_empty() {
+            return Some(RotationOrder::Current);
+        }
+        if let Some(v) = suffix.strip_prefix('.') {
+            return v.parse::<u32>().ok().map(|v| RotationOrder::Numbered(std::cmp::Reverse(v)));
+        }
+        match suffix.strip_prefix('-') {
+            Some(v) if !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()) => Some(RotationOrder::Dated(v.to_string())),
+            _ => None,
+        }
+    }
+}
+
 #[test]
 fn should_create_user_info_struct() {
     let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
@@ -217,7 +657,79 @@ fn should_create_user_info_struct() {
     //let groups_result = SystemInfo::get_user_groups(&mut vfs, "forensicrs");
 
     //let result = UserInfo::get_user_info("forensicrs".to_string(), &mut vfs).expect("Couldn't process bash");
-    let result = UserArtifact::get_user_artifacts("forensicrs".to_string(), &mut vfs).expect("Couldn't process bash");
+    let package_inventory = PackageInventory::load_packages(&mut vfs).unwrap();
+    let result = UserArtifact::get_user_artifacts("forensicrs".to_string(), &mut vfs, &package_inventory).expect("Couldn't process bash");
 
     println!("{:?}", result);
 }
+
+#[test]
+fn should_load_passwd_tolerating_bad_lines() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = std::path::Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    let system_info = SystemInfo::load(&mut vfs).expect("Should load passwd");
+
+    let forensicrs = system_info.users.iter().find(|v| v.name == "forensicrs").unwrap();
+    assert_eq!(1000, forensicrs.gid);
+    assert_eq!("Forensicrs,,,", forensicrs.gecos);
+    assert_eq!(PasswordPlaceholder::Shadowed, forensicrs.password);
+    assert_eq!("forensicrs", forensicrs.groups[0].name);
+    assert!(forensicrs.groups.iter().any(|v| v.name == "sudo"));
+    //the supplementary groups are looked up by the user name, never by the password field
+    let user_info = UserInfo::get_user_info("forensicrs".to_string(), &mut vfs).unwrap();
+    assert_eq!(forensicrs.groups, user_info.groups);
+
+    let error_lines: Vec<usize> = system_info.errors.iter().map(|v| v.line_number).collect();
+    assert_eq!(vec![54, 55], error_lines);
+    assert_eq!("invalid UID abc", system_info.errors[0].reason);
+
+    let nis_entries: Vec<(bool, &str)> = system_info
+        .nis_entries
+        .iter()
+        .map(|v| (v.include, v.name.as_str()))
+        .collect();
+    assert_eq!(vec![(false, "baduser"), (true, "@admins"), (true, "")], nis_entries);
+    assert_eq!(vec!["sss".to_string()], system_info.remote_account_sources);
+}
+
+#[test]
+fn should_read_rotated_family_in_order() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = std::path::Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    let family = rotated_family(&mut vfs, Path::new("/var/log/auth.log"));
+    let family_names: Vec<&str> = family.iter().filter_map(|v| v.file_name()?.to_str()).collect();
+    assert_eq!(
+        vec!["auth.log.4.bz2", "auth.log.3.xz", "auth.log.2.gz", "auth.log.1", "auth.log"],
+        family_names
+    );
+
+    let lines = read_rotated_lines(&mut vfs, Path::new("/var/log/auth.log"));
+    assert!(lines[0].line.contains("192.0.2.200"));
+    assert!(lines[1].line.contains("192.0.2.14"));
+    assert!(lines[2].line.contains("198.51.100.23"));
+    assert_eq!(Path::new("/var/log/auth.log.2.gz"), lines[2].path);
+    assert_eq!(Path::new("/var/log/auth.log"), lines[4].path);
+    assert_eq!(1, lines[4].line_number);
+    assert_eq!(Compression::Xz, Compression::detect(&vfs.read_all(&family[1]).unwrap()));
+}
+
+#[test]
+fn should_stop_decompressing_at_the_limit() {
+    let base_path = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let virtual_file_system = std::path::Path::new(&base_path).join("artifacts");
+
+    let mut _std_vfs = StdVirtualFS::new();
+    let mut vfs = ChRootFileSystem::new(virtual_file_system, Box::new(_std_vfs));
+    for path in ["/var/log/auth.log.4.bz2", "/var/log/auth.log.3.xz", "/var/log/auth.log.2.gz", "/triage/triage.tar.zst"] {
+        let contents = vfs.read_all(Path::new(path)).unwrap();
+        let size = decompress(contents.clone()).unwrap().len() as u64;
+        assert!(decompress_with_limit(contents.clone(), size).is_ok());
+        assert!(matches!(decompress_with_limit(contents, size - 1), Err(ForensicError::BadFormat)));
+    }
+}
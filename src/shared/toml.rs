@@ -0,0 +1,381 @@
+use serde_json::{Map, Value};
+
+//reader of toml configuration files into json values, tables are objects and arrays of tables are arrays of objects.
+//Dates and times are kept as their text
+pub fn parse_toml(contents: &str) -> Option<Value> {
+    let mut parser = TomlParser { text: contents, position: 0 };
+    let mut root = Value::Object(Map::new());
+    let mut table: Vec<String> = Vec::new();
+    loop {
+        parser.skip_blank();
+        if parser.rest().is_empty() {
+            return Some(root);
+        }
+        if parser.eat("[[") {
+            table = parser.key()?;
+            if !parser.eat("]]") {
+                return None;
+            }
+            push_table(&mut root, &table)?;
+        } else if parser.eat("[") {
+            table = parser.key()?;
+            if !parser.eat("]") {
+                return None;
+            }
+            table_at(&mut root, &table)?;
+        } else {
+            let key = parser.key()?;
+            if !parser.eat("=") {
+                return None;
+            }
+            parser.skip_spaces();
+            let value = parser.value()?;
+            insert(&mut root, &table, &key, value)?;
+        }
+        parser.end_of_line()?;
+    }
+}
+
+struct TomlParser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> TomlParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.rest().starts_with(prefix) {
+            self.position += prefix.len();
+            return true;
+        }
+        false
+    }
+
+    //spaces and tabs of the same line
+    fn skip_spaces(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    //spaces, line breaks and comments between statements and inside arrays
+    fn skip_blank(&mut self) {
+        loop {
+            let rest = self.rest();
+            self.position += rest.len() - rest.trim_start().len();
+            if !self.rest().starts_with('#') {
+                return;
+            }
+            self.skip_comment();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        let rest = self.rest();
+        self.position += rest.find('\n').unwrap_or(rest.len());
+    }
+
+    //only a comment can follow a statement on its line
+    fn end_of_line(&mut self) -> Option<()> {
+        self.skip_spaces();
+        if self.rest().starts_with('#') {
+            self.skip_comment();
+        }
+        if self.eat("\n") || self.eat("\r\n") || self.rest().is_empty() {
+            return Some(());
+        }
+        None
+    }
+
+    //bare or quoted keys joined by dots
+    fn key(&mut self) -> Option<Vec<String>> {
+        let mut key = Vec::new();
+        loop {
+            self.skip_spaces();
+            let rest = self.rest();
+            let part = if rest.starts_with('"') {
+                self.basic_string()?
+            } else if rest.starts_with('\'') {
+                self.literal_string()?
+            } else {
+                let length = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+                    .unwrap_or(rest.len());
+                if length == 0 {
+                    return None;
+                }
+                self.position += length;
+                rest[..length].to_string()
+            };
+            key.push(part);
+            self.skip_spaces();
+            if !self.eat(".") {
+                return Some(key);
+            }
+        }
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        let rest = self.rest();
+        if rest.starts_with("\"\"\"") {
+            self.multiline_basic_string().map(Value::String)
+        } else if rest.starts_with("'''") {
+            self.multiline_literal_string().map(Value::String)
+        } else if rest.starts_with('"') {
+            self.basic_string().map(Value::String)
+        } else if rest.starts_with('\'') {
+            self.literal_string().map(Value::String)
+        } else if rest.starts_with('[') {
+            self.array()
+        } else if rest.starts_with('{') {
+            self.inline_table()
+        } else {
+            self.scalar()
+        }
+    }
+
+    //arrays can span several lines and end with a comma
+    fn array(&mut self) -> Option<Value> {
+        self.position += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat("]") {
+                return Some(Value::Array(values));
+            }
+            values.push(self.value()?);
+            self.skip_blank();
+            if !self.eat(",") {
+                return self.eat("]").then_some(Value::Array(values));
+            }
+        }
+    }
+
+    //inline tables are written in a single line
+    fn inline_table(&mut self) -> Option<Value> {
+        self.position += 1;
+        let mut table = Value::Object(Map::new());
+        self.skip_spaces();
+        if self.eat("}") {
+            return Some(table);
+        }
+        loop {
+            let key = self.key()?;
+            if !self.eat("=") {
+                return None;
+            }
+            self.skip_spaces();
+            let value = self.value()?;
+            insert(&mut table, &[], &key, value)?;
+            self.skip_spaces();
+            if self.eat("}") {
+                return Some(table);
+            }
+            if !self.eat(",") {
+                return None;
+            }
+        }
+    }
+
+    //booleans, numbers, and dates that are left as text
+    fn scalar(&mut self) -> Option<Value> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '}' | '#'))
+            .unwrap_or(rest.len());
+        self.position += length;
+        let token = &rest[..length];
+        let number = token.replace('_', "");
+        let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+            .iter()
+            .find_map(|(prefix, radix)| Some((number.strip_prefix(prefix)?, *radix)));
+        match token {
+            "" => None,
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            _ => match radix {
+                Some((digits, radix)) => i64::from_str_radix(digits, radix).ok().map(Value::from),
+                None => number
+                    .parse::<i64>()
+                    .map(Value::from)
+                    .or_else(|_| number.parse::<f64>().map(Value::from))
+                    .ok()
+                    .or_else(|| Some(Value::String(token.to_string()))),
+            },
+        }
+    }
+
+    fn basic_string(&mut self) -> Option<String> {
+        self.position += 1;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((index, character)) = chars.next() {
+            match character {
+                '"' => {
+                    self.position += index + 1;
+                    return Some(value);
+                }
+                '\\' => value.push(unescape(&mut chars)?),
+                '\n' => return None,
+                _ => value.push(character),
+            }
+        }
+        None
+    }
+
+    fn literal_string(&mut self) -> Option<String> {
+        self.position += 1;
+        let rest = self.rest();
+        let end = rest.find(['\'', '\n'])?;
+        if !rest[end..].starts_with('\'') {
+            return None;
+        }
+        self.position += end + 1;
+        Some(rest[..end].to_string())
+    }
+
+    fn multiline_basic_string(&mut self) -> Option<String> {
+        self.position += 3;
+        let body = trim_first_line_break(self.rest());
+        let start = self.rest().len() - body.len();
+        let mut value = String::new();
+        let mut chars = body.char_indices().peekable();
+        while let Some((index, character)) = chars.next() {
+            match character {
+                '"' if body[index..].starts_with("\"\"\"") => {
+                    let quotes = closing_quotes(&body[index..], '"')?;
+                    value.push_str(&"\"".repeat(quotes - 3));
+                    self.position += start + index + quotes;
+                    return Some(value);
+                }
+                //a backslash at the end of a line joins it with the next text
+                '\\' if ends_line(&body[index + 1..]) => {
+                    while chars.next_if(|(_, v)| v.is_whitespace()).is_some() {}
+                }
+                '\\' => value.push(unescape(&mut chars)?),
+                _ => value.push(character),
+            }
+        }
+        None
+    }
+
+    fn multiline_literal_string(&mut self) -> Option<String> {
+        self.position += 3;
+        let body = trim_first_line_break(self.rest());
+        let end = body.find("'''")?;
+        let quotes = closing_quotes(&body[end..], '\'')?;
+        self.position += self.rest().len() - body.len() + end + quotes;
+        Some(body[..end + quotes - 3].to_string())
+    }
+}
+
+//a line break right after the opening delimiter of a multi-line string is not part of it
+fn trim_first_line_break(text: &str) -> &str {
+    text.strip_prefix('\n').or_else(|| text.strip_prefix("\r\n")).unwrap_or(text)
+}
+
+fn ends_line(text: &str) -> bool {
+    let text = text.trim_start_matches([' ', '\t']);
+    text.starts_with('\n') || text.starts_with("\r\n")
+}
+
+//up to two quotes next to the closing delimiter belong to the string
+fn closing_quotes(text: &str, quote: char) -> Option<usize> {
+    let quotes = text.len() - text.trim_start_matches(quote).len();
+    (quotes <= 5).then_some(quotes)
+}
+
+fn unescape(chars: &mut impl Iterator<Item = (usize, char)>) -> Option<char> {
+    let digits = match chars.next()?.1 {
+        'b' => return Some('\u{8}'),
+        't' => return Some('\t'),
+        'n' => return Some('\n'),
+        'f' => return Some('\u{c}'),
+        'r' => return Some('\r'),
+        'e' => return Some('\u{1b}'),
+        '"' => return Some('"'),
+        '\\' => return Some('\\'),
+        'u' => 4,
+        'U' => 8,
+        _ => return None,
+    };
+    let code: String = chars.take(digits).map(|v| v.1).collect();
+    if code.chars().count() != digits {
+        return None;
+    }
+    char::from_u32(u32::from_str_radix(&code, 16).ok()?)
+}
+
+//the table of a header or of a dotted key, created when missing. An array of tables gives its last table
+fn table_at<'v>(root: &'v mut Value, path: &[String]) -> Option<&'v mut Map<String, Value>> {
+    let mut current = root;
+    for key in path {
+        let next = current.as_object_mut()?.entry(key.clone()).or_insert_with(|| Value::Object(Map::new()));
+        current = match next {
+            Value::Array(v) => v.last_mut()?,
+            v => v,
+        };
+    }
+    current.as_object_mut()
+}
+
+fn push_table(root: &mut Value, path: &[String]) -> Option<()> {
+    let (last, parent) = path.split_last()?;
+    let tables = table_at(root, parent)?.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new()));
+    tables.as_array_mut()?.push(Value::Object(Map::new()));
+    Some(())
+}
+
+//a key defined twice makes the file invalid
+fn insert(root: &mut Value, table: &[String], key: &[String], value: Value) -> Option<()> {
+    let (last, parent) = key.split_last()?;
+    let path: Vec<String> = table.iter().chain(parent).cloned().collect();
+    let table = table_at(root, &path)?;
+    if table.contains_key(last) {
+        return None;
+    }
+    table.insert(last.clone(), value);
+    Some(())
+}
+
+#[cfg(test)]
+mod toml_tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_toml() {
+        let contents = r#"
+title = "a \"quoted\" \u00e9"  # comment
+path = 'C:\temp'
+[server]
+ports = [ 8000,
+  0x1F,  # hex
+  8002, ]
+limits = { cpu = 1.5, memory.max = "1G" }
+script = """
+echo \
+  done"""
+[[plugins]]
+name = "first"
+[[plugins]]
+name = "second"
+[plugins.options]
+enabled = true
+"#;
+        let value = parse_toml(contents).expect("Should parse toml");
+        assert_eq!("a \"quoted\" é", value["title"]);
+        assert_eq!("C:\\temp", value["path"]);
+        assert_eq!(serde_json::json!([8000, 31, 8002]), value["server"]["ports"]);
+        assert_eq!(1.5, value["server"]["limits"]["cpu"]);
+        assert_eq!("1G", value["server"]["limits"]["memory"]["max"]);
+        assert_eq!("echo done", value["server"]["script"]);
+        assert_eq!("first", value["plugins"][0]["name"]);
+        assert_eq!(true, value["plugins"][1]["options"]["enabled"]);
+
+        assert!(parse_toml("key = 1\nkey = 2").is_none());
+        assert!(parse_toml("key = [1, 2").is_none());
+        assert!(parse_toml("key = 1 2").is_none());
+    }
+}